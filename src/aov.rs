@@ -0,0 +1,122 @@
+//
+// AOVs ("arbitrary output variables"): what the camera rays hit first, saved alongside
+// the image -- for debugging (no more commenting in a normals-as-colors line in trace),
+// compositing, and guiding the denoiser
+//
+// Each sample's AOVs go through the same film (and filter) as its color, so edges come
+// out antialiased, and a pixel's alpha is how much of it hit something. EXRs get them as
+// extra layers (depth.Z, normal.X, ...); other formats get a file each next to the image
+// (/tmp/smoothcanvas.normal.png), PNGs squeezed into something viewable.
+//
+
+use crate::*;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aov {
+    Depth,    // distance along the ray to the first hit
+    Normal,   // world space, facing the camera
+    Albedo,   // the material's color there (see Material::albedo)
+    Position, // world space
+    Material, // a color per material (by what it is, so identical materials share one)
+    Object,   // a color per Jumble name (the innermost one hit)
+}
+
+impl FromStr for Aov {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Aov::ALL.iter().copied().find(|aov| aov.name() == s)
+            .ok_or(format!("unknown aov: {} (try depth, normal, albedo, position, material or object)", s))
+    }
+}
+
+impl Aov {
+    pub const ALL: [Aov; 6] = [Aov::Depth, Aov::Normal, Aov::Albedo, Aov::Position, Aov::Material, Aov::Object];
+
+    // comma separated names, or all, or none
+    pub fn parse_list(s: &str) -> Result<Vec<Aov>, String> {
+        match s {
+            "all" => Ok(Aov::ALL.to_vec()),
+            "none" | "" => Ok(Vec::new()),
+            _ => s.split(',').map(|name| name.trim().parse()).collect(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::Position => "position",
+            Aov::Material => "material",
+            Aov::Object => "object",
+        }
+    }
+
+    // EXR channel names (the layer's name goes in front)
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            Aov::Depth => &["Z"],
+            Aov::Normal | Aov::Position => &["X", "Y", "Z"],
+            Aov::Albedo | Aov::Material | Aov::Object => &["R", "G", "B"],
+        }
+    }
+
+    // this AOV for a camera ray that hit (alpha 1; misses are all 0s)
+    pub fn value(&self, ray: Ray, hit: &HitRecord) -> Color {
+        let v = |v: Vec3| Color::new([v.x(), v.y(), v.z()]);
+        match self {
+            Aov::Depth => { let d = hit.t * ray.dir.len(); Color::new([d, d, d]) },
+            Aov::Normal => v(hit.normal.normalize()),
+            Aov::Albedo => hit.material.albedo(hit),
+            Aov::Position => v(hit.point),
+            Aov::Material => id_color(fnv1a(hit.material.log().bytes()) as u32),
+            Aov::Object => id_color(hit.object),
+        }
+    }
+
+    pub fn miss() -> Color {
+        Color::new_alpha([0.0; 4])
+    }
+
+    // img (rgba, as rendered) made viewable as a PNG: normals from [-1,1] to [0,1],
+    // depth and position stretched over the range they cover (nearest/lowest black)
+    pub fn visualize(&self, img: &[f32]) -> Vec<f32> {
+        let mut out = img.to_vec();
+        match self {
+            Aov::Normal => {
+                for px in out.chunks_mut(4) {
+                    let coverage = px[3]; // (edges are a mix with a miss, which stays black)
+                    for c in px.iter_mut().take(3) {
+                        *c = 0.5 * (*c + coverage);
+                    }
+                }
+            },
+            Aov::Depth | Aov::Position => {
+                // range over the fully covered pixels (edges are part miss, so shrunk toward 0)
+                let (mut lo, mut hi) = ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]);
+                for px in img.chunks(4).filter(|px| px[3] > 0.999) {
+                    for c in 0..3 {
+                        lo[c] = lo[c].min(px[c]);
+                        hi[c] = hi[c].max(px[c]);
+                    }
+                }
+                for px in out.chunks_mut(4) {
+                    for c in 0..3 {
+                        let range = hi[c] - lo[c];
+                        px[c] = if range > 0.0 { ((px[c] - lo[c]*px[3]) / range).clamp(0.0, 1.0) } else { px[3] };
+                    }
+                }
+            },
+            Aov::Albedo | Aov::Material | Aov::Object => (),
+        }
+        out
+    }
+}
+
+// a bright, random-looking (but always the same) color for an id
+pub fn id_color(id: u32) -> Color {
+    let h = fnv1a(id.to_le_bytes()) as u32;
+    let c = |shift: u32| 0.2 + 0.8 * ((h >> shift) & 0xff) as f32 / 255.0;
+    Color::new([c(0), c(8), c(16)])
+}
@@ -0,0 +1,227 @@
+//
+// bounding volume hierarchy
+//
+// Lets a Jumble skip most of its children instead of asking every one of them
+// whether it was hit (and a TriangleMesh most of its triangles). Built top-down
+// with the surface area heuristic (SAH), binning centroids along the longest axis.
+//
+
+use crate::*;
+
+const NUM_BINS: usize = 12;
+// leaves hold at most this many (fewer if a split's cheaper), unless their centroids all
+// coincide and there's nothing to split them by
+const MAX_LEAF_SIZE: usize = 4;
+const TRAVERSAL_COST: f32 = 0.125; // relative to intersecting one child
+
+#[derive(Debug)]
+enum BvhNode {
+    // objects order[start..start+count]
+    Leaf { bbox: AABoundingBox, start: usize, count: usize },
+    // left child is the next node, right child is at `right`
+    Interior { bbox: AABoundingBox, right: usize, axis: usize },
+}
+
+impl BvhNode {
+    fn bbox(&self) -> &AABoundingBox {
+        match self {
+            BvhNode::Leaf { bbox, .. } | BvhNode::Interior { bbox, .. } => bbox,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    order: Vec<usize>,     // indices of the bounded objects, arranged so each leaf is a range
+    unbounded: Vec<usize>, // objects with infinite (or unknown) bounds, always tested
+    bbox: AABoundingBox,   // bounds of everything, unbounded included
+}
+
+// what the build needs to know about each object
+struct Prim {
+    bbox: AABoundingBox,
+    centroid: Vec3,
+}
+
+impl Bvh {
+    pub fn build(objs: &[Arc<dyn Intersectable>]) -> Bvh {
+        let bounds: Vec<AABoundingBox> = objs.iter().map(|obj| obj.bbox()).collect();
+        Bvh::from_bounds(&bounds)
+    }
+
+    // build over anything, given the bounds of each of its pieces
+    pub fn from_bounds(bounds: &[AABoundingBox]) -> Bvh {
+        let mut prims = Vec::with_capacity(bounds.len());
+        let mut order = Vec::with_capacity(bounds.len());
+        let mut unbounded = Vec::new();
+        let mut bbox = AABoundingBox::empty();
+        for (i, &b) in bounds.iter().enumerate() {
+            bbox = bbox.union(&b);
+            if b.is_finite() {
+                order.push(i);
+            } else if !b.is_empty() {
+                unbounded.push(i);
+            }
+            prims.push(Prim { bbox: b, centroid: b.centroid() });
+        }
+
+        let mut bvh = Bvh { nodes: Vec::new(), order, unbounded, bbox };
+        if !bvh.order.is_empty() {
+            let n = bvh.order.len();
+            bvh.nodes.reserve(2*n);
+            bvh.build_node(&prims, 0, n);
+        }
+        bvh
+    }
+
+    pub fn bbox(&self) -> AABoundingBox {
+        self.bbox
+    }
+
+    pub fn depth(&self) -> usize {
+        fn depth_of(nodes: &[BvhNode], n: usize) -> usize {
+            match nodes[n] {
+                BvhNode::Leaf { .. } => 1,
+                BvhNode::Interior { right, .. } => 1 + depth_of(nodes, n+1).max(depth_of(nodes, right)),
+            }
+        }
+        if self.nodes.is_empty() { 0 } else { depth_of(&self.nodes, 0) }
+    }
+
+    // builds the subtree for order[start..end], returns its index
+    fn build_node(&mut self, prims: &[Prim], start: usize, end: usize) -> usize {
+        let idx = self.nodes.len();
+        let count = end - start;
+        let order = &mut self.order[start..end];
+
+        let mut bbox = AABoundingBox::empty();
+        let mut centroids = AABoundingBox::empty();
+        for &i in order.iter() {
+            bbox = bbox.union(&prims[i].bbox);
+            centroids = centroids.grow(prims[i].centroid);
+        }
+
+        let axis = centroids.longest_axis();
+        let cmin = centroids.min[axis];
+        let cext = centroids.extent()[axis];
+        if count == 1 || cext <= 0.0 {
+            // nothing to split (or nothing to tell them apart by)
+            self.nodes.push(BvhNode::Leaf { bbox, start, count });
+            return idx;
+        }
+
+        // bin the centroids
+        let bin_of = |c: Vec3| (((c[axis] - cmin) / cext * NUM_BINS as f32) as usize).min(NUM_BINS-1);
+        let mut bins = [(AABoundingBox::empty(), 0usize); NUM_BINS];
+        for &i in order.iter() {
+            let b = &mut bins[bin_of(prims[i].centroid)];
+            b.0 = b.0.union(&prims[i].bbox);
+            b.1 += 1;
+        }
+
+        // sweep from the right to get the cost of everything right of each split...
+        let mut right_cost = [0.0f32; NUM_BINS];
+        let (mut rbox, mut rcount) = (AABoundingBox::empty(), 0);
+        for b in (1..NUM_BINS).rev() {
+            rbox = rbox.union(&bins[b].0);
+            rcount += bins[b].1;
+            right_cost[b] = rbox.surface_area() * rcount as f32;
+        }
+        // ...then from the left to find the cheapest (split after bin `best`)
+        let (mut lbox, mut lcount) = (AABoundingBox::empty(), 0);
+        let (mut best, mut best_cost) = (0, f32::INFINITY);
+        for b in 0..NUM_BINS-1 {
+            lbox = lbox.union(&bins[b].0);
+            lcount += bins[b].1;
+            let cost = lbox.surface_area() * lcount as f32 + right_cost[b+1];
+            if cost < best_cost {
+                best = b;
+                best_cost = cost;
+            }
+        }
+        let area = bbox.surface_area();
+        let split_cost = TRAVERSAL_COST * area + best_cost;
+        let leaf_cost = area * count as f32;
+
+        if count <= MAX_LEAF_SIZE && leaf_cost <= split_cost {
+            self.nodes.push(BvhNode::Leaf { bbox, start, count });
+            return idx;
+        }
+
+        // partition by bin, falling back to a median split if everything landed on one side
+        let mut mid = 0;
+        for j in 0..count {
+            if bin_of(prims[order[j]].centroid) <= best {
+                order.swap(j, mid);
+                mid += 1;
+            }
+        }
+        if mid == 0 || mid == count {
+            mid = count / 2;
+            order.select_nth_unstable_by(mid, |&a, &b| {
+                prims[a].centroid[axis].partial_cmp(&prims[b].centroid[axis]).unwrap()
+            });
+        }
+
+        self.nodes.push(BvhNode::Interior { bbox, right: 0, axis }); // right is fixed up below
+        self.build_node(prims, start, start + mid);
+        let right_idx = self.build_node(prims, start + mid, end);
+        if let BvhNode::Interior { right, .. } = &mut self.nodes[idx] {
+            *right = right_idx;
+        }
+        idx
+    }
+
+    // same contract as Intersectable::intersect, for the objects this was built from
+    pub fn intersect(&self, objs: &[Arc<dyn Intersectable>],
+                     ray: Ray, rng: &Range, hit: &mut HitRecord, indent_by: usize) -> Shot {
+        self.traverse(ray, rng, hit, |i, hit| objs[i].intersect(ray, rng, hit, indent_by))
+    }
+
+    // calls intersect_piece(i, hit) for each piece i whose bounds the ray might pass through
+    // before hit.t, and hits if any of them did
+    pub fn traverse<F>(&self, ray: Ray, rng: &Range, hit: &mut HitRecord, mut intersect_piece: F) -> Shot
+    where F: FnMut(usize, &mut HitRecord) -> Shot {
+        let mut hit_something = false;
+        for &i in &self.unbounded {
+            if let Shot::Hit = intersect_piece(i, hit) {
+                hit_something = true;
+            }
+        }
+
+        if !self.nodes.is_empty() {
+            let inv_dir = ray.dir.invert();
+            let mut stack: Vec<usize> = Vec::with_capacity(64);
+            stack.push(0);
+            while let Some(n) = stack.pop() {
+                let node = &self.nodes[n];
+                // hit.t only shrinks, so later boxes have to beat the closest hit so far
+                if !node.bbox().hit(ray.origin, inv_dir, rng.min, rng.max.min(hit.t)) {
+                    continue;
+                }
+                match *node {
+                    BvhNode::Leaf { start, count, .. } => {
+                        for &i in &self.order[start..start+count] {
+                            if let Shot::Hit = intersect_piece(i, hit) {
+                                hit_something = true;
+                            }
+                        }
+                    },
+                    BvhNode::Interior { right, axis, .. } => {
+                        // visit the nearer child first (it's on top of the stack)
+                        if ray.dir[axis] < 0.0 {
+                            stack.push(n+1);
+                            stack.push(right);
+                        } else {
+                            stack.push(right);
+                            stack.push(n+1);
+                        }
+                    },
+                }
+            }
+        }
+
+        if hit_something { Shot::Hit } else { Shot::Miss }
+    }
+}
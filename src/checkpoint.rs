@@ -0,0 +1,171 @@
+//
+// checkpoints: a progressive render's state on disk, so a long render can be stopped
+// (or killed) and picked up again later with --resume
+//
+// What's saved is the film (each pixel's weighted color sums), how many samples each
+// pixel's had and how much they varied (so adaptive sampling knows which pixels have
+// settled down). That's all there is to the random numbers too: each pixel's come from
+// the seed, where it is and which sample it's on (see render::pixel_rng), so carrying on
+// from the same counts gives the same samples an uninterrupted render would've taken.
+//
+// Resuming with different settings (or a changed scene) would quietly mix two different
+// images, so checkpoints carry a fingerprint of everything that goes into the picture --
+// for a scene file that's its contents and those of every obj, mtl and texture it read --
+// and a mismatch is refused. Things that only change how it's saved (output, tone
+// mapping, ...) or how fast it goes (threads, time limit, ...) can differ.
+//
+// The file (little endian):
+//   "rtrtrck2"  fingerprint: u64  width, height, pass: u32
+//   then, for every pixel (bottom row first): r, g, b, a sums (of the image, then each
+//   AOV, see aov.rs), weight: f32  samples: u32  mean, m2: f32 (see film::PixelVariance)
+//
+
+use crate::*;
+use crate::settings::{RenderSettings, SceneKind};
+use crate::film::{Film, PixelVariance};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+const MAGIC: &[u8; 8] = b"rtrtrck2";
+
+// where a (progressive) render is at
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub film: Film,
+    pub counts: Vec<u32>,              // samples taken per pixel (x + y*width, bottom row first)
+    pub variance: Vec<PixelVariance>,  // of those samples, per pixel (the same way round)
+    pub pass: u32,                     // passes finished
+}
+
+impl Checkpoint {
+    // the very beginning
+    pub fn new(settings: &RenderSettings) -> Self {
+        let (width, height) = (settings.image_width, settings.image_height);
+        Self { film: Film::new(width, height, settings.filter, 1 + settings.rendered_aovs().len()),
+               counts: vec![0; (width * height) as usize],
+               variance: vec![PixelVariance::default(); (width * height) as usize],
+               pass: 0 }
+    }
+
+    pub fn count(&self, px: [u32; 2]) -> u32 {
+        self.counts[(px[1] * self.film.width + px[0]) as usize]
+    }
+
+    pub fn variance(&self, px: [u32; 2]) -> PixelVariance {
+        self.variance[(px[1] * self.film.width + px[0]) as usize]
+    }
+
+    // (written next to path first, then moved over it, so a kill halfway through doesn't
+    // cost the last good checkpoint too)
+    pub fn save(&self, path: &str, fingerprint: u64) -> Result<(), String> {
+        let err = |e: &dyn std::fmt::Display| format!("can't write checkpoint {}: {}", path, e);
+        let tmp = format!("{}.tmp", path);
+        let file = File::create(&tmp).map_err(|e| err(&e))?;
+        let mut w = BufWriter::new(file);
+        let write = |w: &mut BufWriter<File>| -> std::io::Result<()> {
+            w.write_all(MAGIC)?;
+            w.write_all(&fingerprint.to_le_bytes())?;
+            for v in [self.film.width, self.film.height, self.pass] {
+                w.write_all(&v.to_le_bytes())?;
+            }
+            for y in 0..self.film.height {
+                for x in 0..self.film.width {
+                    let (sums, weight) = self.film.raw(x, y);
+                    for sum in sums {
+                        for c in 0..4 {
+                            w.write_all(&sum[c].to_le_bytes())?;
+                        }
+                    }
+                    w.write_all(&weight.to_le_bytes())?;
+                    w.write_all(&self.count([x, y]).to_le_bytes())?;
+                    let variance = self.variance([x, y]);
+                    w.write_all(&variance.mean.to_le_bytes())?;
+                    w.write_all(&variance.m2.to_le_bytes())?;
+                }
+            }
+            w.flush()
+        };
+        write(&mut w).map_err(|e| err(&e))?;
+        drop(w);
+        fs::rename(&tmp, path).map_err(|e| err(&e))
+    }
+
+    // a checkpoint of a render with these settings (and fingerprint), or why not
+    pub fn load(path: &str, settings: &RenderSettings, fingerprint: u64) -> Result<Self, String> {
+        let err = |e: &dyn std::fmt::Display| format!("can't read checkpoint {}: {}", path, e);
+        let file = File::open(path).map_err(|e| err(&e))?;
+        let mut r = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic).map_err(|e| err(&e))?;
+        if &magic != MAGIC {
+            return Err(format!("{} isn't a checkpoint", path));
+        }
+        if read_u64(&mut r).map_err(|e| err(&e))? != fingerprint {
+            return Err(format!("{} is from a render with different settings or a different scene; \
+                                resuming would mix the two (start over instead)", path));
+        }
+        let mut state = Self::new(settings);
+        let (width, height) = (read_u32(&mut r).map_err(|e| err(&e))?, read_u32(&mut r).map_err(|e| err(&e))?);
+        if width != settings.image_width || height != settings.image_height {
+            // (the fingerprint should've caught this)
+            return Err(format!("{} is {}x{}, not {}x{}", path, width, height, settings.image_width, settings.image_height));
+        }
+        state.pass = read_u32(&mut r).map_err(|e| err(&e))?;
+        let layers = state.film.layers;
+        let read_pixel = |r: &mut BufReader<File>| -> std::io::Result<(Vec<Color>, f32, PixelVariance)> {
+            let mut sums = Vec::with_capacity(layers);
+            for _ in 0..layers {
+                sums.push(Color::new_alpha([read_f32(r)?, read_f32(r)?, read_f32(r)?, read_f32(r)?]));
+            }
+            let weight = read_f32(r)?;
+            Ok((sums, weight, PixelVariance { n: read_u32(r)?, mean: read_f32(r)?, m2: read_f32(r)? }))
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let (sums, weight, variance) = read_pixel(&mut r).map_err(|e| err(&e))?;
+                state.film.set_raw(x, y, &sums, weight);
+                state.counts[(y * width + x) as usize] = variance.n;
+                state.variance[(y * width + x) as usize] = variance;
+            }
+        }
+        Ok(state)
+    }
+}
+
+fn read_u32<R: Read>(r: &mut R) -> std::io::Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> std::io::Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+fn read_f32<R: Read>(r: &mut R) -> std::io::Result<f32> {
+    Ok(f32::from_bits(read_u32(r)?))
+}
+
+// everything that goes into the picture, boiled down
+pub fn fingerprint(settings: &RenderSettings, camera: &Camera) -> u64 {
+    let scene: Box<dyn Iterator<Item = u8>> = match &settings.scene {
+        SceneKind::File(path) => {
+            // (just the scene file itself if nobody said what else it read)
+            let files = if settings.scene_files.is_empty() { vec![PathBuf::from(path)] } else { settings.scene_files.clone() };
+            // each one's length too, so bytes moving from one file to the next don't go unnoticed
+            Box::new(files.into_iter().flat_map(|file| {
+                let bytes = fs::read(file).unwrap_or_default();
+                (bytes.len() as u64).to_le_bytes().to_vec().into_iter().chain(bytes)
+            }))
+        },
+        kind => Box::new(format!("{:?}", kind).into_bytes().into_iter()),
+    };
+    let picture = format!("{}x{} {} spp, depth {}, {:?} {:?} {:?} {:?} seed {} debug {} aovs {:?} {:?}",
+                          settings.image_width, settings.image_height, settings.samples_per_pixel,
+                          settings.max_depth, settings.refl_type, settings.background, settings.sampler,
+                          settings.filter, settings.seed, settings.debug, settings.rendered_aovs(), camera);
+    fnv1a(picture.bytes().chain(scene))
+}
@@ -0,0 +1,98 @@
+//
+// command line interface
+//
+// turns `rtrtr --book --spp 50 -o /tmp/book.png` into RenderSettings
+//
+
+use rtrtr::settings::RenderSettings;
+
+pub const USAGE: &str = "\
+usage: rtrtr [options]
+
+presets (applied first, in any order):
+  --debug                trace a couple of pixels and print everything
+  --lite                 quick preview (5 spp)
+  --book                 match Shirley's RTiOW configs
+  --final                match RTiOW final image (and render its scene)
+
+options (applied in order, overriding presets and scene file settings):
+  -w, --width <px>       image width
+  -h, --height <px>      image height (adjusts aspect)
+  -a, --aspect <w/h>     aspect ratio, e.g. 1.5 or 16/9 (adjusts height)
+  -s, --spp <n>          samples per pixel
+  -d, --max-depth <n>    max ray bounces
+  -r, --refl <type>      in-sphere | on-sphere | hemisphere
+  -b, --background <bg>  sky | black | white | r,g,b | r,g,b:r,g,b (bottom:top gradient)
+      --sampler <type>   independent | stratified | halton | sobol (default sobol)
+  -f, --filter <type>    box | tent | gaussian | mitchell | lanczos, optionally :<radius> in pixels
+                         (default box, i.e. box:0.5, a plain average of each pixel's samples)
+      --adaptive <err>   keep sampling each pixel, spp at a time, until its relative error is
+                         under err (e.g. 0.02) or it hits --max-spp (default 0: off)
+      --max-spp <n>      most samples a pixel gets when adaptive (default 0: 8x spp)
+      --heatmap <path>   also save how many samples each pixel got, as an image
+      --snapshot <when>  render progressively, in passes of 1, 2, 4, ... spp, saving the image
+                         so far every <n>s seconds or <n> passes (default never)
+      --time-limit <s>   render progressively, stopping after this many seconds (or at --spp)
+      --checkpoint <path>
+                         render progressively, saving the render's state there after each pass
+      --resume <path>    carry on from a checkpoint (the same scene and options it was started with)
+  -j, --threads <n>      render threads (default 0: one per core)
+      --tile-size <px>   size of the square tiles handed to each thread (default 16)
+      --seed <n>         random seed; the same seed and options render the same image (default 0)
+      --scene <name>     main | final | path to a scene file
+  -o, --output <path>    output image, .png or linear .exr, .hdr, .pfm (default /tmp/smoothcanvas.png)
+      --exr <type>       half | float (default half)
+      --tonemap <op>     clamp | reinhard | reinhard:<white> | aces, for png output (default clamp)
+      --exposure <ev>    brighten (or darken, if negative) by this many stops before tone mapping
+      --bit-depth <n>    8 | 16 bits per png channel (default 8)
+      --aovs <list>      also save what camera rays hit first: comma separated depth, normal,
+                         albedo, position, material, object (by jumble name), or all (default none)
+      --denoise <n>      smooth out noise with n passes of an edge-aware filter, guided by
+                         normals, albedo and depth (up to 10; default 0: off; 5 is a good start)
+      --noisy <path>     also save the image as rendered, before denoising
+      --help             print this and quit";
+
+const PRESETS: [&str; 4] = ["--debug", "--lite", "--book", "--final"];
+
+// parse the given args (not including the program name)
+pub fn parse_args(args: &[String]) -> Result<RenderSettings, String> {
+    let mut settings = presets(args);
+    apply_args(&mut settings, args)?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+// first pass: presets
+pub fn presets(args: &[String]) -> RenderSettings {
+    let has = |flag: &str| args.iter().any(|a| a == flag);
+    RenderSettings::preset(has(PRESETS[0]), has(PRESETS[1]), has(PRESETS[2]), has(PRESETS[3]))
+}
+
+// second pass: everything else
+pub fn apply_args(settings: &mut RenderSettings, args: &[String]) -> Result<(), String> {
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        if PRESETS.contains(&arg.as_str()) {
+            continue;
+        }
+        let key = match arg.as_str() {
+            "-w" => "width",
+            "-h" => "height",
+            "-a" => "aspect",
+            "-s" => "spp",
+            "-d" => "max-depth",
+            "-r" => "refl",
+            "-b" => "background",
+            "-f" => "filter",
+            "-j" => "threads",
+            "-o" => "output",
+            long => long.strip_prefix("--").ok_or(format!("unknown option: {}", arg))?,
+        };
+        if !RenderSettings::KEYS.contains(&key) {
+            return Err(format!("unknown option: {}", arg));
+        }
+        let val = it.next().ok_or(format!("missing value for {}", arg))?;
+        settings.set(key, val).map_err(|e| format!("{}: {}", arg, e))?;
+    }
+    Ok(())
+}
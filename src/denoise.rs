@@ -0,0 +1,160 @@
+//
+// denoiser: makes a low spp render presentable
+//
+// An edge-avoiding à-trous wavelet filter (Dammertz et al., "Edge-Avoiding À-Trous Wavelet
+// Transform for fast Global Illumination Filtering", 2010): a few passes of a 5x5 blur with
+// holes in it (1, 2, 4, ... pixels apart, so it reaches far quickly), where each neighbor
+// only counts as much as it looks like the same surface -- facing the same way (normal AOV),
+// about as far away (depth AOV) and not too different a color (going by a slightly blurred
+// copy, so a lone firefly doesn't count as an edge and get left alone). Texture detail is
+// taken out first (divided by the albedo AOV) and put back afterwards, so only the lighting
+// gets blurred.
+//
+// NOTE: it only sees the finished image, so snapshots and checkpoints stay noisy
+//
+
+use crate::*;
+use crate::aov::Aov;
+use crate::render::Image;
+
+// how picky each edge stop is (smaller is pickier)
+const SIGMA_COLOR: f32 = 0.2;    // of (compressed, blurred) color differences...
+const COLOR_DECAY: f32 = 0.7;    // ...which tightens by this much every pass
+const NORMAL_POWER: f32 = 64.0;  // cos between normals, to this power
+const SIGMA_DEPTH: f32 = 0.05;   // of relative depth differences, per pixel of reach
+
+// more passes than this reach further than any image is wide (1024 pixels apart), and the
+// color stop gets so picky that nothing counts but the pixel itself
+pub const MAX_ITERATIONS: u32 = 10;
+
+// the B3 spline (1 4 6 4 1)/16
+const KERNEL: [f32; 5] = [1.0/16.0, 1.0/4.0, 3.0/8.0, 1.0/4.0, 1.0/16.0];
+
+// img's colors (rgba, top row first), denoised with iterations passes; the normal, albedo
+// and depth AOVs steer it (without them it's a plainer, blurrier, color-only filter)
+pub fn denoise(img: &Image, iterations: u32) -> Vec<f32> {
+    let (width, height) = (img.width as usize, img.height as usize);
+    let n = width * height;
+    let aov = |which: Aov| img.aovs.iter().find(|(aov, _)| *aov == which).map(|(_, data)| data);
+
+    // per pixel guides; misses (alpha 0) count as white, facing nowhere, infinitely far
+    let mut albedo = vec![[1.0f32; 3]; n];
+    let mut normal = vec![[0.0f32; 3]; n];
+    let mut depth = vec![f32::INFINITY; n];
+    for i in 0..n {
+        if let Some(data) = aov(Aov::Albedo) {
+            let miss = 1.0 - data[4*i + 3].clamp(0.0, 1.0);
+            albedo[i] = [0, 1, 2].map(|c| (data[4*i + c] + miss).max(0.01)); // (can't divide by black)
+        }
+        if let Some(data) = aov(Aov::Normal) {
+            let v = Vec3::new([data[4*i], data[4*i + 1], data[4*i + 2]]);
+            if !v.near_zero() {
+                let v = v.normalize();
+                normal[i] = [v.x(), v.y(), v.z()];
+            }
+        }
+        if let Some(data) = aov(Aov::Depth) {
+            let coverage = data[4*i + 3];
+            if coverage > 0.0 {
+                depth[i] = data[4*i] / coverage;
+            }
+        }
+    }
+    let guided_by_normals = aov(Aov::Normal).is_some();
+    let guided_by_depth = aov(Aov::Depth).is_some();
+
+    // the lighting alone
+    let mut light: Vec<[f32; 3]> = (0..n).map(|i| [0, 1, 2].map(|c| img.data[4*i + c] / albedo[i][c])).collect();
+
+    for pass in 0..iterations.min(MAX_ITERATIONS) {
+        let step = 1i64 << pass;
+        let sigma_color = SIGMA_COLOR * COLOR_DECAY.powi(pass as i32);
+        let guide = blur3x3(&light, width, height);
+        let mut next = vec![[0.0f32; 3]; n];
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let p = y as usize * width + x as usize;
+                let cp = guide[p];
+                let (mut sum, mut total) = ([0.0f32; 3], 0.0f32);
+                for (j, kj) in KERNEL.iter().enumerate() {
+                    let qy = y + (j as i64 - 2) * step;
+                    if qy < 0 || qy >= height as i64 {
+                        continue;
+                    }
+                    for (i, ki) in KERNEL.iter().enumerate() {
+                        let qx = x + (i as i64 - 2) * step;
+                        if qx < 0 || qx >= width as i64 {
+                            continue;
+                        }
+                        let q = qy as usize * width + qx as usize;
+
+                        let cq = guide[q];
+                        let dc = (0..3).map(|c| (cp[c] - cq[c]).powi(2)).sum::<f32>();
+                        let mut w = ki * kj * (-dc / (sigma_color * sigma_color)).exp();
+                        if guided_by_normals {
+                            let cos: f32 = (0..3).map(|c| normal[p][c] * normal[q][c]).sum();
+                            w *= cos.max(0.0).powf(NORMAL_POWER);
+                        }
+                        if guided_by_depth {
+                            w *= depth_weight(depth[p], depth[q], step as f32 * (((i as i64 - 2).pow(2) + (j as i64 - 2).pow(2)) as f32).sqrt());
+                        }
+                        for c in 0..3 {
+                            sum[c] += w * light[q][c];
+                        }
+                        total += w;
+                    }
+                }
+                // (the center always counts, so total > 0 unless something's nan)
+                next[p] = if total > 0.0 { sum.map(|s| s / total) } else { light[p] };
+            }
+        }
+        light = next;
+    }
+
+    // texture back on
+    let mut out = img.data.clone();
+    for i in 0..n {
+        for c in 0..3 {
+            out[4*i + c] = light[i][c] * albedo[i][c];
+        }
+    }
+    out
+}
+
+// Reinhard per channel, so bright and dark bits get compared on an even footing
+fn compress(c: [f32; 3]) -> [f32; 3] {
+    c.map(|v| { let v = v.max(0.0); v / (1.0 + v) })
+}
+
+// how alike two depths are, allowing more difference the further apart the pixels are
+fn depth_weight(zp: f32, zq: f32, pixels: f32) -> f32 {
+    match (zp.is_finite(), zq.is_finite()) {
+        (true, true) => {
+            let tolerance = SIGMA_DEPTH * zp.max(1e-4) * pixels.max(1.0);
+            (-(zp - zq).abs() / tolerance).exp()
+        },
+        (false, false) => 1.0, // both sky
+        _ => 0.0,
+    }
+}
+
+// compressed, and averaged with the 8 pixels around it
+fn blur3x3(light: &[[f32; 3]], width: usize, height: usize) -> Vec<[f32; 3]> {
+    let mut out = vec![[0.0f32; 3]; light.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let (mut sum, mut count) = ([0.0f32; 3], 0.0);
+            for qy in y.saturating_sub(1)..(y + 2).min(height) {
+                for qx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let c = compress(light[qy*width + qx]);
+                    for i in 0..3 {
+                        sum[i] += c[i];
+                    }
+                    count += 1.0;
+                }
+            }
+            out[y*width + x] = sum.map(|s| s / count);
+        }
+    }
+    out
+}
@@ -0,0 +1,254 @@
+//
+// film: where samples pile up into pixels
+//
+// Each sample lands somewhere in its pixel and adds to every pixel whose filter reaches
+// that far (weighted by how far away it is), and each pixel is the weighted average of
+// what it got. A box filter half a pixel wide is the plain per pixel average; the wider,
+// smoother ones trade a touch of blur for less aliasing and noise, and Mitchell and
+// Lanczos (with their negative lobes) keep edges crisp.
+//
+
+use crate::*;
+use std::str::FromStr;
+
+// all separable, with radii in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box { radius: f32 },
+    Tent { radius: f32 },
+    Gaussian { radius: f32, sigma: f32 },
+    Mitchell { radius: f32, b: f32, c: f32 },
+    Lanczos { radius: f32 },
+}
+
+impl FromStr for Filter {
+    type Err = String;
+    // box | tent | gaussian | mitchell | lanczos, each optionally :<radius>
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, radius) = match s.split_once(':') {
+            Some((name, r)) => match r.parse::<f32>() {
+                Ok(r) if r > 0.0 && r.is_finite() => (name, Some(r)),
+                _ => return Err(format!("invalid filter radius: {}", r)),
+            },
+            None => (s, None),
+        };
+        let filter = match name {
+            "box" => Filter::Box { radius: 0.5 },
+            "tent" => Filter::Tent { radius: 1.0 },
+            "gaussian" => Filter::Gaussian { radius: 1.5, sigma: 0.5 },
+            "mitchell" => Filter::Mitchell { radius: 2.0, b: 1.0/3.0, c: 1.0/3.0 },
+            "lanczos" => Filter::Lanczos { radius: 2.0 },
+            _ => return Err(format!("unknown filter: {} (try box, tent, gaussian, mitchell or lanczos)", s)),
+        };
+        Ok(match radius { Some(r) => filter.with_radius(r), None => filter })
+    }
+}
+
+impl Filter {
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Filter::Box { radius } | Filter::Tent { radius } | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. } | Filter::Lanczos { radius } => radius,
+        }
+    }
+
+    // the same shape, stretched (a gaussian's sigma keeps its proportion to the radius)
+    pub fn with_radius(self, r: f32) -> Self {
+        match self {
+            Filter::Box { .. } => Filter::Box { radius: r },
+            Filter::Tent { .. } => Filter::Tent { radius: r },
+            Filter::Gaussian { radius, sigma } => Filter::Gaussian { radius: r, sigma: sigma * r / radius },
+            Filter::Mitchell { b, c, .. } => Filter::Mitchell { radius: r, b, c },
+            Filter::Lanczos { .. } => Filter::Lanczos { radius: r },
+        }
+    }
+
+    // weight of a sample dx, dy pixels from a pixel's center
+    pub fn eval(&self, dx: f32, dy: f32) -> f32 {
+        self.eval_1d(dx) * self.eval_1d(dy)
+    }
+
+    fn eval_1d(&self, x: f32) -> f32 {
+        let r = self.radius();
+        if x.abs() > r {
+            return 0.0;
+        }
+        match *self {
+            Filter::Box { .. } => if x < r { 1.0 } else { 0.0 }, // (so samples on an edge only count once)
+            Filter::Tent { .. } => r - x.abs(),
+            Filter::Gaussian { sigma, .. } => {
+                let g = |x: f32| (-x*x / (2.0*sigma*sigma)).exp();
+                (g(x) - g(r)).max(0.0) // (shifted down so it reaches 0 at the radius)
+            },
+            Filter::Mitchell { b, c, .. } => {
+                // Mitchell and Netravali's cubic, which spans [-2,2]
+                let x = (2.0 * x / r).abs();
+                if x < 1.0 {
+                    ((12.0 - 9.0*b - 6.0*c)*x*x*x + (-18.0 + 12.0*b + 6.0*c)*x*x + (6.0 - 2.0*b)) / 6.0
+                } else {
+                    ((-b - 6.0*c)*x*x*x + (6.0*b + 30.0*c)*x*x + (-12.0*b - 48.0*c)*x + (8.0*b + 24.0*c)) / 6.0
+                }
+            },
+            Filter::Lanczos { .. } => sinc(x) * sinc(x / r),
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 { 1.0 } else { (PI*x).sin() / (PI*x) }
+}
+
+// A window of an image's pixels (x from the left, y from the bottom, like the camera)
+// and the weighted sums of the samples that reached them. Render threads each fill one
+// for their tile (plus the filter's reach around it), which then get merged. Samples
+// can carry more than a color (see aov.rs): each is a layer, filtered the same way.
+#[derive(Debug, Clone)]
+pub struct Film {
+    pub width: u32,  // of the whole image
+    pub height: u32,
+    pub filter: Filter,
+    pub layers: usize, // colors per sample (the image is the first)
+    window: [u32; 4],  // x0, y0, x1, y1 (exclusive)
+    sums: Vec<Color>,  // layers per pixel
+    weights: Vec<f32>,
+}
+
+impl Film {
+    // the whole image
+    pub fn new(width: u32, height: u32, filter: Filter, layers: usize) -> Self {
+        Self::window(width, height, filter, layers, [0, 0, width, height])
+    }
+
+    // just the pixels in window (clipped to the image)
+    pub fn window(width: u32, height: u32, filter: Filter, layers: usize, window: [u32; 4]) -> Self {
+        let window = [window[0].min(width), window[1].min(height), window[2].min(width), window[3].min(height)];
+        let n = ((window[2].saturating_sub(window[0])) * (window[3].saturating_sub(window[1]))) as usize;
+        Self { width, height, filter, layers, window,
+               sums: vec![Color::new_alpha([0.0; 4]); n * layers], weights: vec![0.0; n] }
+    }
+
+    // the pixels samples taken inside of pixels could reach
+    pub fn around(width: u32, height: u32, filter: Filter, layers: usize, pixels: &[[u32; 2]]) -> Self {
+        let reach = (filter.radius() - 0.5).max(0.0).ceil() as u32;
+        let (mut lo, mut hi) = ([u32::MAX; 2], [0u32; 2]);
+        for px in pixels {
+            for a in 0..2 {
+                lo[a] = lo[a].min(px[a]);
+                hi[a] = hi[a].max(px[a] + 1);
+            }
+        }
+        if pixels.is_empty() {
+            return Self::window(width, height, filter, layers, [0, 0, 0, 0]);
+        }
+        Self::window(width, height, filter, layers, [lo[0].saturating_sub(reach), lo[1].saturating_sub(reach),
+                                                     hi[0] + reach, hi[1] + reach])
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        let [x0, y0, x1, y1] = self.window;
+        if x < x0 || x >= x1 || y < y0 || y >= y1 {
+            return None;
+        }
+        Some(((y - y0) * (x1 - x0) + (x - x0)) as usize)
+    }
+
+    // a sample at pos (in pixels, so pixel [i, j] spans [i, i+1] x [j, j+1]), one color per layer
+    pub fn add_sample(&mut self, pos: [f32; 2], colors: &[Color]) {
+        let r = self.filter.radius();
+        let range = |p: f32, n: u32| {
+            let lo = (p - 0.5 - r).ceil().max(0.0) as u32;
+            let hi = (p - 0.5 + r).floor().min(n as f32 - 1.0);
+            (lo, if hi < 0.0 { None } else { Some(hi as u32) })
+        };
+        let ((x_lo, x_hi), (y_lo, y_hi)) = (range(pos[0], self.width), range(pos[1], self.height));
+        let (x_hi, y_hi) = match (x_hi, y_hi) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return,
+        };
+        for y in y_lo..=y_hi {
+            for x in x_lo..=x_hi {
+                let w = self.filter.eval(pos[0] - (x as f32 + 0.5), pos[1] - (y as f32 + 0.5));
+                if w == 0.0 {
+                    continue;
+                }
+                if let Some(i) = self.index(x, y) {
+                    for (sum, color) in self.sums[i*self.layers..(i + 1)*self.layers].iter_mut().zip(colors) {
+                        *sum += w * *color;
+                    }
+                    self.weights[i] += w;
+                }
+            }
+        }
+    }
+
+    // add in what another film collected
+    pub fn merge(&mut self, other: &Film) {
+        let [x0, y0, x1, y1] = other.window;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if let (Some(i), Some(j)) = (self.index(x, y), other.index(x, y)) {
+                    for l in 0..self.layers.min(other.layers) {
+                        self.sums[i*self.layers + l] += other.sums[j*other.layers + l];
+                    }
+                    self.weights[i] += other.weights[j];
+                }
+            }
+        }
+    }
+
+    // what pixel [x, y] (which has to be in the window) has collected so far, for checkpoints
+    pub fn raw(&self, x: u32, y: u32) -> (&[Color], f32) {
+        let i = self.index(x, y).expect("pixel outside the film");
+        (&self.sums[i*self.layers..(i + 1)*self.layers], self.weights[i])
+    }
+
+    pub fn set_raw(&mut self, x: u32, y: u32, sums: &[Color], weight: f32) {
+        if let Some(i) = self.index(x, y) {
+            self.sums[i*self.layers..(i + 1)*self.layers].copy_from_slice(sums);
+            self.weights[i] = weight;
+        }
+    }
+
+    // the filtered color of pixel [x, y] (black if nothing reached it)
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.layer_pixel(0, x, y)
+    }
+
+    // ...and of one of its other layers (all 0s if nothing reached it)
+    pub fn layer_pixel(&self, layer: usize, x: u32, y: u32) -> Color {
+        match self.index(x, y) {
+            Some(i) if self.weights[i] != 0.0 => self.sums[i*self.layers + layer] / self.weights[i],
+            _ if layer == 0 => Color::black(),
+            _ => Color::new_alpha([0.0; 4]),
+        }
+    }
+}
+
+// Running mean and variance of a pixel's sample brightness (Welford's method, which doesn't
+// lose precision the way summing squares does), for deciding when it's seen enough samples
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelVariance {
+    pub n: u32,
+    pub mean: f32,
+    pub m2: f32, // sum of squared differences from the mean
+}
+
+impl PixelVariance {
+    pub fn add(&mut self, c: Color) {
+        let x = c.luminance();
+        self.n += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.n as f32;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub fn variance(&self) -> f32 {
+        if self.n < 2 { f32::INFINITY } else { self.m2 / (self.n - 1) as f32 }
+    }
+
+    // standard error of the mean over the mean, i.e. how far off the pixel probably still is
+    // (plus a little, so near black pixels don't chase noise nobody would see)
+    pub fn relative_error(&self) -> f32 {
+        (self.variance() / self.n as f32).sqrt() / (self.mean.abs() + 0.01)
+    }
+}
@@ -2,43 +2,273 @@
 // Read/write stuff n' things
 //
 
-use std::io::{stdout, BufWriter};
+use crate::settings::RenderSettings;
+use crate::tonemap::{exposure_scale, encode_srgb, ToneMap};
+use crate::render::Image;
+use crate::utils::Color;
+use std::io::{stdout, BufWriter, Write};
+use std::path::Path;
+use std::fs::File;
+use std::str::FromStr;
 
-pub fn write_img(filename: &str, img: Vec<f32>, width: u32, height: u32) {
+// what to save an image as, going by the file's extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,           // 8 or 16 bits, tone mapped and sRGB encoded (for looking at)
+    Exr(ExrPixel), // OpenEXR, linear, uncompressed (for compositing and tone mapping later)
+    Hdr,           // Radiance RGBE, linear
+    Pfm,           // portable float map, linear
+}
+
+// how big the numbers in an EXR are
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExrPixel {
+    Half,  // 16 bit floats, plenty for color and half the size
+    Float, // 32 bit, for when it all has to come back out exactly
+}
+
+impl FromStr for ExrPixel {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "half" => Ok(ExrPixel::Half),
+            "float" => Ok(ExrPixel::Float),
+            _ => Err(format!("invalid exr pixel type: {} (try half or float)", s)),
+        }
+    }
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &str, exr: ExrPixel) -> Result<Self, String> {
+        let ext = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        match ext.as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "exr" => Ok(ImageFormat::Exr(exr)),
+            "hdr" => Ok(ImageFormat::Hdr),
+            "pfm" => Ok(ImageFormat::Pfm),
+            _ => Err(format!("don't know how to save {} (try .png, .exr, .hdr or .pfm)", path)),
+        }
+    }
+}
+
+// save img (linear rgba, top row first) in whatever format filename's extension asks for
+pub fn write_img(filename: &str, img: &[f32], width: u32, height: u32, settings: &RenderSettings) -> Result<(), String> {
     assert!(img.len() == (width * height * 4) as usize); // rgba
 
-    // For reading and opening files
-    use std::path::Path;
-    use std::fs::File;
+    let format = ImageFormat::from_path(filename, settings.exr)?;
+    let err = |e: &dyn std::fmt::Display| format!("can't write {}: {}", filename, e);
+    let file = File::create(Path::new(filename)).map_err(|e| err(&e))?;
+    let mut w = BufWriter::new(file);
+    match format {
+        ImageFormat::Png => write_png(&mut w, img, width, height, settings).map_err(|e| err(&e))?,
+        ImageFormat::Exr(pixel) => write_exr(&mut w, &rgba_channels("", img), width, height, pixel).map_err(|e| err(&e))?,
+        ImageFormat::Hdr => write_hdr(&mut w, img, width, height).map_err(|e| err(&e))?,
+        ImageFormat::Pfm => write_pfm(&mut w, img, width, height).map_err(|e| err(&e))?,
+    }
+    w.flush().map_err(|e| err(&e))
+}
+
+// save a render and its AOVs (see aov.rs): all in one file if it's an EXR, else each AOV
+// in its own file next to it (/tmp/out.png's normals in /tmp/out.normal.png). Only the
+// AOVs settings asks for are saved (the denoiser may have had others rendered).
+pub fn write_image(filename: &str, img: &Image, settings: &RenderSettings) -> Result<(), String> {
+    let format = ImageFormat::from_path(filename, settings.exr)?;
+    if let ImageFormat::Exr(pixel) = format {
+        let err = |e: &dyn std::fmt::Display| format!("can't write {}: {}", filename, e);
+        let mut channels = rgba_channels("", &img.data);
+        for (aov, data) in img.aovs.iter().filter(|(aov, _)| settings.aovs.contains(aov)) {
+            channels.extend(aov.channels().iter().enumerate()
+                            .map(|(c, name)| (format!("{}.{}", aov.name(), name), data.as_slice(), c)));
+        }
+        let file = File::create(Path::new(filename)).map_err(|e| err(&e))?;
+        let mut w = BufWriter::new(file);
+        write_exr(&mut w, &channels, img.width, img.height, pixel).map_err(|e| err(&e))?;
+        return w.flush().map_err(|e| err(&e));
+    }
 
+    write_img(filename, &img.data, img.width, img.height, settings)?;
     let path = Path::new(filename);
-    let file = File::create(path).unwrap();
-    let ref mut w = BufWriter::new(file);
+    let plain = RenderSettings { exposure: 0.0, tonemap: ToneMap::Clamp, ..settings.clone() }; // (data, not light)
+    for (aov, data) in img.aovs.iter().filter(|(aov, _)| settings.aovs.contains(aov)) {
+        let aov_path = path.with_extension(format!("{}.{}", aov.name(),
+                                                   path.extension().and_then(|e| e.to_str()).unwrap_or("")));
+        let data = if format == ImageFormat::Png { aov.visualize(data) } else { data.clone() };
+        write_img(&aov_path.to_string_lossy(), &data, img.width, img.height, &plain)?;
+    }
+    Ok(())
+}
 
+// tone mapped, sRGB encoded, 8 or 16 bits (see tonemap.rs)
+fn write_png<W: Write>(w: &mut W, img: &[f32], width: u32, height: u32, settings: &RenderSettings) -> Result<(), png::EncodingError> {
     let mut encoder = png::Encoder::new(w, width, height);
     encoder.set_color(png::ColorType::Rgba);
-    encoder.set_depth(png::BitDepth::Eight);
-    encoder.set_trns(vec!(0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8));
-    encoder.set_source_gamma(png::ScaledFloat::from_scaled(45455)); // 1.0 / 2.2, scaled by 100000
-    encoder.set_source_gamma(png::ScaledFloat::new(1.0 / 2.2));     // 1.0 / 2.2, unscaled, but rounded
-    let source_chromaticities = png::SourceChromaticities::new(     // Using unscaled instantiation here
-        (0.31270, 0.32900),
-        (0.64000, 0.33000),
-        (0.30000, 0.60000),
-        (0.15000, 0.06000)
-    );
-    encoder.set_source_chromaticities(source_chromaticities);
-
-    // convert floats to chars and apply gamma correction
-    // γ (gamma) = 2.2, color saved = c^(1/γ), estimate γ as 2.0, so color = c^(1/2) = sqrt(c)
-    let mut data: Vec<u8> = Vec::with_capacity(img.len());
-    unsafe { data.set_len(data.capacity()); }
-    for i in 0..img.len() {
-        data[i] = ((256f32-f32::EPSILON) * f32::sqrt(img[i])) as u8; 
-    }
-
-    let mut writer = encoder.write_header().unwrap();
-    writer.write_image_data(&data).unwrap(); // Save
+    encoder.set_depth(if settings.bit_depth == 16 { png::BitDepth::Sixteen } else { png::BitDepth::Eight });
+    // (no tRNS chunk: it's only allowed for gray, rgb and palette images -- rgba has alpha
+    // already -- and decoders, the png crate's included, refuse to read one that has it)
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    // plus the gAMA and cHRM the spec says go with sRGB, for decoders that don't know it
+    // (values from https://www.w3.org/TR/png/#11sRGB, scaled by 100000)
+    let scaled = png::ScaledFloat::from_scaled;
+    encoder.set_source_gamma(scaled(45455));
+    encoder.set_source_chromaticities(png::SourceChromaticities {
+        white: (scaled(31270), scaled(32900)),
+        red: (scaled(64000), scaled(33000)),
+        green: (scaled(30000), scaled(60000)),
+        blue: (scaled(15000), scaled(6000)),
+    });
+
+    let exposure = exposure_scale(settings.exposure);
+    let max = if settings.bit_depth == 16 { 65535.0 } else { 255.0 };
+    let mut data: Vec<u8> = Vec::with_capacity(img.len() * settings.bit_depth as usize / 8);
+    for px in img.chunks(4) {
+        let c = settings.tonemap.apply(exposure * Color::new([px[0], px[1], px[2]]));
+        let alpha = if px[3] > 0.0 { px[3].min(1.0) } else { 0.0 };
+        for v in [encode_srgb(c[0]), encode_srgb(c[1]), encode_srgb(c[2]), alpha] {
+            let v = (v * max).round();
+            if settings.bit_depth == 16 {
+                data.extend((v as u16).to_be_bytes());
+            } else {
+                data.push(v as u8);
+            }
+        }
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data) // Save
+}
+
+// Scanline OpenEXR, no compression: magic and version, a header of named attributes,
+// a table of where each scanline starts, then the scanlines (each channel's row in turn).
+// See "The OpenEXR File Layout" (openexr.com) for the gory details.
+// the R, G, B and A channels of an rgba image, in layer (if any)
+fn rgba_channels<'a>(layer: &str, img: &'a [f32]) -> Vec<(String, &'a [f32], usize)> {
+    let prefix = if layer.is_empty() { String::new() } else { format!("{}.", layer) };
+    ["R", "G", "B", "A"].iter().enumerate().map(|(c, name)| (format!("{}{}", prefix, name), img, c)).collect()
+}
+
+// channels are (name, rgba image, which of its components)
+fn write_exr<W: Write>(w: &mut W, channels: &[(String, &[f32], usize)], width: u32, height: u32, pixel: ExrPixel) -> std::io::Result<()> {
+    let (pixel_type, size): (i32, usize) = match pixel { ExrPixel::Half => (1, 2), ExrPixel::Float => (2, 4) };
+    let mut channels = channels.to_vec();
+    channels.sort_by(|a, b| a.0.cmp(&b.0)); // (readers want them sorted by name)
+
+    let mut header: Vec<u8> = Vec::new();
+    header.extend(0x01312f76u32.to_le_bytes()); // magic
+    header.extend(2u32.to_le_bytes());          // version 2, single part scanlines
+    let mut attribute = |name: &str, kind: &str, value: &[u8]| {
+        header.extend(name.as_bytes());
+        header.push(0);
+        header.extend(kind.as_bytes());
+        header.push(0);
+        header.extend((value.len() as i32).to_le_bytes());
+        header.extend(value);
+    };
+    let mut chlist: Vec<u8> = Vec::new();
+    for (name, _, _) in channels.iter() {
+        chlist.extend(name.as_bytes());
+        chlist.push(0);
+        chlist.extend(pixel_type.to_le_bytes());
+        chlist.extend([0, 0, 0, 0]);           // pLinear and reserved
+        chlist.extend(1i32.to_le_bytes());     // x sampling
+        chlist.extend(1i32.to_le_bytes());     // y sampling
+    }
+    chlist.push(0);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1].iter().flat_map(|v| v.to_le_bytes()).collect();
+    attribute("channels", "chlist", &chlist);
+    attribute("compression", "compression", &[0]); // none
+    attribute("dataWindow", "box2i", &window);
+    attribute("displayWindow", "box2i", &window);
+    attribute("lineOrder", "lineOrder", &[0]);     // increasing y (top row first, like img)
+    attribute("pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute("screenWindowCenter", "v2f", &[0u8; 8]);
+    attribute("screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+    w.write_all(&header)?;
+
+    // offsets of the scanlines, from the start of the file
+    let line_size = 8 + channels.len() * width as usize * size;
+    let first = header.len() + 8 * height as usize;
+    for y in 0..height as usize {
+        w.write_all(&((first + y*line_size) as u64).to_le_bytes())?;
+    }
+
+    let mut line: Vec<u8> = Vec::with_capacity(line_size);
+    for y in 0..height as usize {
+        line.clear();
+        line.extend((y as i32).to_le_bytes());
+        line.extend(((line_size - 8) as i32).to_le_bytes());
+        for &(_, img, c) in channels.iter() {
+            for x in 0..width as usize {
+                let v = img[4*(y*width as usize + x) + c];
+                match pixel {
+                    ExrPixel::Half => line.extend(f32_to_half(v).to_le_bytes()),
+                    ExrPixel::Float => line.extend(v.to_le_bytes()),
+                }
+            }
+        }
+        w.write_all(&line)?;
+    }
+    Ok(())
+}
+
+// nearest IEEE half (ties to even), overflowing to infinity
+fn f32_to_half(v: f32) -> u16 {
+    let bits = v.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x7f_ffff;
+    if exp == 0xff {
+        return sign | 0x7c00 | if mant != 0 { 0x200 } else { 0 }; // inf, nan
+    }
+    let e = exp - 127 + 15;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if e <= 0 {
+        // too small for a normal half: a subnormal one, or zero
+        if e < -10 {
+            return sign;
+        }
+        let m = mant | 0x80_0000;
+        let shift = (14 - e) as u32;
+        return sign | ((m + (1 << (shift - 1)) - 1 + ((m >> shift) & 1)) >> shift) as u16;
+    }
+    let rounded = ((e as u32) << 10) + ((mant + 0xfff + ((mant >> 13) & 1)) >> 13); // (can carry into e)
+    if rounded >= 0x7c00 { sign | 0x7c00 } else { sign | rounded as u16 }
+}
+
+// Radiance RGBE: a shared exponent and three 8 bit mantissas per pixel (flat, not run-length encoded)
+fn write_hdr<W: Write>(w: &mut W, img: &[f32], width: u32, height: u32) -> std::io::Result<()> {
+    write!(w, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)?;
+    let mut line: Vec<u8> = Vec::with_capacity(4 * width as usize);
+    for row in img.chunks(4 * width as usize) {
+        line.clear();
+        for px in row.chunks(4) {
+            let rgb = [px[0], px[1], px[2]].map(|c| if c > 0.0 { c } else { 0.0 }); // (also nan)
+            let max = rgb[0].max(rgb[1]).max(rgb[2]);
+            if max < 1e-32 || !max.is_finite() {
+                line.extend([0, 0, 0, 0]);
+                continue;
+            }
+            // max = m * 2^e with m in [0.5, 1)
+            let e = max.log2().floor() as i32 + 1;
+            let scale = 256.0 / 2f32.powi(e);
+            line.extend(rgb.map(|c| (c * scale).min(255.0) as u8));
+            line.push((e + 128).clamp(0, 255) as u8);
+        }
+        w.write_all(&line)?;
+    }
+    Ok(())
+}
+
+// portable float map: a tiny text header, then little endian rgb floats, bottom row first
+fn write_pfm<W: Write>(w: &mut W, img: &[f32], width: u32, height: u32) -> std::io::Result<()> {
+    write!(w, "PF\n{} {}\n-1.0\n", width, height)?; // (a negative scale means little endian)
+    for row in img.chunks(4 * width as usize).rev() {
+        let line: Vec<u8> = row.chunks(4).flat_map(|px| [px[0], px[1], px[2]]).flat_map(|c| c.to_le_bytes()).collect();
+        w.write_all(&line)?;
+    }
+    Ok(())
 }
 
 use ferris_says::say;
@@ -51,3 +281,116 @@ pub fn conclude(msg: &str) {
     say(message.as_bytes(), width, &mut writer).unwrap();
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn half_rounding() {
+        let tiny = 2f32.powi(-25); // halfway between 0 and the smallest subnormal half
+        for &(v, h) in &[(0.0, 0x0000), (-0.0, 0x8000), (1.0, 0x3c00), (-2.0, 0xc000),
+                         (65504.0, 0x7bff),                  // biggest half
+                         (65520.0, 0x7c00),                  // halfway to the next, so to infinity
+                         (2f32.powi(-24), 0x0001),            // smallest subnormal
+                         (tiny, 0x0000),                     // tie, to even
+                         (f32::from_bits(tiny.to_bits() + 1), 0x0001),
+                         (2f32.powi(-14), 0x0400),            // smallest normal
+                         (f32::INFINITY, 0x7c00), (f32::NEG_INFINITY, 0xfc00)] {
+            assert_eq!(f32_to_half(v), h, "for {:e}", v);
+        }
+        let nan = f32_to_half(f32::NAN);
+        assert!(nan & 0x7c00 == 0x7c00 && nan & 0x3ff != 0, "NaN became {:#06x}", nan);
+    }
+
+    // a 2×2 rgba image (its values are all exact in half)
+    const IMG: [f32; 16] = [0.0, 0.5, 1.0, 1.0,   2.0, -0.25, 4.0, 1.0,
+                            0.125, 8.0, 16.0, 1.0, 1.5, 3.0, -1.0, 0.5];
+
+    fn u32_at(b: &[u8], at: usize) -> u32 { u32::from_le_bytes(b[at..at+4].try_into().unwrap()) }
+    fn f32_at(b: &[u8], at: usize) -> f32 { f32::from_le_bytes(b[at..at+4].try_into().unwrap()) }
+
+    // (name, type, value) of each header attribute, and where the header ends
+    fn exr_attributes(b: &[u8]) -> (Vec<(String, String, Vec<u8>)>, usize) {
+        let mut at = 8;
+        let cstr = |at: &mut usize| {
+            let end = *at + b[*at..].iter().position(|&c| c == 0).unwrap();
+            let s = String::from_utf8(b[*at..end].to_vec()).unwrap();
+            *at = end + 1;
+            s
+        };
+        let mut attributes = Vec::new();
+        loop {
+            let name = cstr(&mut at);
+            if name.is_empty() {
+                return (attributes, at);
+            }
+            let kind = cstr(&mut at);
+            let len = u32_at(b, at) as usize;
+            attributes.push((name, kind, b[at+4..at+4+len].to_vec()));
+            at += 4 + len;
+        }
+    }
+
+    #[test]
+    fn exr_header() {
+        for &(pixel, pixel_type, size) in &[(ExrPixel::Half, 1i32, 2), (ExrPixel::Float, 2, 4)] {
+            let mut b: Vec<u8> = Vec::new();
+            write_exr(&mut b, &rgba_channels("", &IMG), 2, 2, pixel).unwrap();
+            assert_eq!(&b[0..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+            let (attributes, end) = exr_attributes(&b);
+            let value = |name: &str| attributes.iter().find(|a| a.0 == name).unwrap_or_else(|| panic!("no {}", name));
+            let mut chlist = Vec::new();
+            for name in ["A", "B", "G", "R"] {
+                chlist.extend(name.as_bytes());
+                chlist.extend([0]);
+                chlist.extend(pixel_type.to_le_bytes());
+                chlist.extend([0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+            }
+            chlist.push(0);
+            assert_eq!(value("channels").1, "chlist");
+            assert_eq!(value("channels").2, chlist);
+            assert_eq!(value("compression").2, [0]);
+            let window: Vec<u8> = [0i32, 0, 1, 1].iter().flat_map(|v| v.to_le_bytes()).collect();
+            assert_eq!(value("dataWindow").2, window);
+            assert_eq!(value("displayWindow").2, window);
+            assert_eq!(value("lineOrder").2, [0]);
+
+            // the offset table points at each scanline: y, byte count, then A, B, G, R rows
+            let line_size = 8 + 4 * 2 * size;
+            assert_eq!(b.len(), end + 2*8 + 2*line_size);
+            for y in 0..2 {
+                let offset = u64::from_le_bytes(b[end + 8*y..end + 8*y + 8].try_into().unwrap()) as usize;
+                assert_eq!(offset, end + 2*8 + y*line_size);
+                assert_eq!(u32_at(&b, offset), y as u32);
+                assert_eq!(u32_at(&b, offset + 4) as usize, line_size - 8);
+                for (i, c) in [3, 2, 1, 0].iter().enumerate() {
+                    for x in 0..2 {
+                        let at = offset + 8 + (2*i + x) * size;
+                        let v = IMG[4*(2*y + x) + c];
+                        match pixel {
+                            ExrPixel::Half => assert_eq!(&b[at..at+2], &f32_to_half(v).to_le_bytes()),
+                            ExrPixel::Float => assert_eq!(f32_at(&b, at), v),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pfm_header() {
+        let mut b: Vec<u8> = Vec::new();
+        write_pfm(&mut b, &IMG, 2, 2).unwrap();
+        let header = b"PF\n2 2\n-1.0\n";
+        assert_eq!(&b[..header.len()], header);
+        assert_eq!(b.len(), header.len() + 2*2*3*4);
+
+        // bottom row first, rgb without the alpha
+        let floats: Vec<f32> = b[header.len()..].chunks(4).map(|c| f32_at(c, 0)).collect();
+        let expected: Vec<f32> = IMG.chunks(8).rev().flat_map(|row| row.chunks(4).flat_map(|px| px[..3].to_vec())).collect();
+        assert_eq!(floats, expected);
+    }
+}
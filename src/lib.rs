@@ -0,0 +1,73 @@
+// Rusty the Ray Tracer, the library
+//
+// Everything but the command line lives here so other tools can trace rays too:
+//
+//   let settings = RenderSettings::default();
+//   let camera = Camera::init(&settings, 0.001, 40.0, look_from, look_at, vup, focus);
+//   let img = rtrtr::render(&scene::build_scene(), &camera, &settings);
+//   io::write_image(&settings.output, &img, &settings).unwrap();
+//
+// It doesn't print anything (unless settings.debug); render_with reports progress to a
+// callback instead, which is how main.rs shows it.
+//
+
+// TODO periodically disable these; it's just hard to develop with them
+#![allow(dead_code)]
+#![allow(unused_variables)]
+#![allow(unused_imports)]
+//#![allow(non_snake_case)]
+// and these are just the way I like to write things
+#![allow(clippy::unused_unit)]
+#![allow(clippy::needless_return)]
+#![allow(clippy::neg_multiply)]
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::identity_op)]
+
+pub mod utils;
+pub mod objects;
+pub mod camera;
+pub mod scene;
+pub mod io;
+pub mod materials;
+pub mod settings;
+pub mod scenefile;
+pub mod render;
+pub mod bvh;
+pub mod mesh;
+pub mod obj;
+pub mod lights;
+pub mod textures;
+pub mod microfacet;
+pub mod principled;
+pub mod tonemap;
+pub mod sampler;
+pub mod film;
+pub mod checkpoint;
+pub mod aov;
+pub mod denoise;
+pub mod transform;
+
+pub use crate::utils::*;
+pub use crate::objects::*;
+pub use crate::camera::*;
+pub use crate::mesh::{Triangle, TriangleMesh, MeshVertices};
+pub use crate::materials::{Material, LightScatter, Lambertian, Shiny, Transparent, DiffuseLight, Medium, MediumStack};
+pub use crate::settings::{RenderSettings, SceneKind, Background, Snapshot};
+pub use crate::render::{render, resume, render_with, Progress, Image};
+pub use crate::film::{Film, Filter};
+pub use crate::checkpoint::Checkpoint;
+pub use crate::aov::Aov;
+pub use crate::transform::{Transform, Quaternion, Decomposition};
+pub use crate::microfacet::{Conductor, Dielectric};
+pub use crate::principled::Principled;
+pub use crate::textures::{Texture, SolidColor, Checker, ImageTexture, NoiseTexture, NoiseKind};
+pub use crate::obj::load_obj;
+
+use std::convert::TryFrom;
+use std::convert::TryInto;
+use std::f32::consts::PI;
+
+// consts
+pub const PI_4: f32 = PI / 4.0;
+pub const PI_3: f32 = PI / 3.0;
+pub const PI_2: f32 = PI / 2.0;
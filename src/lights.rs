@@ -0,0 +1,245 @@
+//
+// lights, for sampling directly
+//
+// Finding small lights by bouncing around at random takes forever, so every
+// emissive sphere and triangle in the scene is gathered (in world space) up front,
+// and each bounce also aims a shadow ray at a random point on one of them
+// ("next event estimation"). See ray_color in render.rs for how the two are combined.
+//
+
+use crate::*;
+use crate::bvh::Bvh;
+use crate::mesh::intersect_triangle;
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy)]
+pub enum LightShape {
+    // radius can be negative (normals point in), like Sphere; axes turns world directions
+    // back into the sphere's own (for its uvs), give or take a scale
+    Sphere { center: Vec3, radius: f32, axes: Matrix },
+    // front face wound counterclockwise, like Triangle, with a uv for each corner
+    Triangle { p: [Vec3; 3], uvs: [Vec2; 3] },
+}
+
+#[derive(Debug)]
+pub struct Light {
+    pub shape: LightShape,
+    pub material: Arc<dyn Material>,
+}
+
+impl Light {
+    pub fn sphere(center: Vec3, radius: f32, axes: Matrix, material: Arc<dyn Material>) -> Light {
+        Light { shape: LightShape::Sphere { center, radius, axes }, material }
+    }
+
+    pub fn triangle(p: [Vec3; 3], uvs: [Vec2; 3], material: Arc<dyn Material>) -> Light {
+        Light { shape: LightShape::Triangle { p, uvs }, material }
+    }
+
+    fn bbox(&self) -> AABoundingBox {
+        match self.shape {
+            LightShape::Sphere { center, radius, .. } => {
+                let r = Vec3::new([radius.abs(); 3]);
+                AABoundingBox::new(center - r, center + r)
+            },
+            LightShape::Triangle { p, .. } => AABoundingBox::new(p[0], p[0]).grow(p[1]).grow(p[2]),
+        }
+    }
+
+    // a direction (unit length) from origin toward a random point on the light
+    fn sample_dir<R: Rng + ?Sized>(&self, origin: Vec3, rng: &mut R) -> Vec3 {
+        match self.shape {
+            LightShape::Sphere { center, radius, .. } => {
+                let to_center = center - origin;
+                let dist_squared = to_center.len_squared();
+                let r = radius.abs();
+                if dist_squared > r*r {
+                    // uniformly within the cone of directions the sphere covers
+                    let cos_max = (1.0 - r*r/dist_squared).max(0.0).sqrt();
+                    let z = 1.0 + rng.gen::<f32>() * (cos_max - 1.0);
+                    let phi = 2.0*PI * rng.gen::<f32>();
+                    let sin = (1.0 - z*z).max(0.0).sqrt();
+                    let w = to_center.normalize();
+                    let (u, v) = orthonormal_basis(w);
+                    (sin*phi.cos()*u + sin*phi.sin()*v + z*w).normalize()
+                } else {
+                    // inside: anywhere on it
+                    (center + r*random_unit_vector(rng) - origin).normalize()
+                }
+            },
+            LightShape::Triangle { p, .. } => {
+                let s = rng.gen::<f32>().sqrt();
+                let t = rng.gen::<f32>();
+                let point = (1.0 - s)*p[0] + s*(1.0 - t)*p[1] + s*t*p[2];
+                (point - origin).normalize()
+            },
+        }
+    }
+
+    // distance along unit dir from origin to the light, and its (outward) normal and uv there
+    fn hit(&self, origin: Vec3, dir: Vec3) -> Option<(f32, Vec3, Vec2)> {
+        let rng = Range::default();
+        match self.shape {
+            LightShape::Sphere { center, radius, axes } => {
+                let oc = origin - center;
+                let half_b = oc.dot(dir);
+                let c = oc.len_squared() - radius*radius;
+                let discriminant = half_b*half_b - c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let disqrt = discriminant.sqrt();
+                let t = if rng.inside(-half_b - disqrt) { -half_b - disqrt } else { -half_b + disqrt };
+                if t.outside(&rng) {
+                    return None;
+                }
+                let out = origin + t*dir - center;
+                Some((t, out / radius, Sphere::uv(axes.apply_to_vector(out).normalize())))
+            },
+            LightShape::Triangle { p, uvs } => {
+                let (t, bary) = intersect_triangle(&Ray::new(origin, dir), p[0], p[1], p[2], &rng, f32::INFINITY)?;
+                let uv = bary[0]*uvs[0] + bary[1]*uvs[1] + bary[2]*uvs[2];
+                Some((t, (p[1] - p[0]).cross(p[2] - p[0]).normalize(), uv))
+            },
+        }
+    }
+
+    // density (over solid angle) of sample_dir picking unit dir from origin
+    fn pdf(&self, origin: Vec3, dir: Vec3) -> f32 {
+        let (t, normal, _) = match self.hit(origin, dir) {
+            Some(hit) => hit,
+            None => return 0.0,
+        };
+        match self.shape {
+            LightShape::Sphere { center, radius, .. } => {
+                let dist_squared = (center - origin).len_squared();
+                let r = radius.abs();
+                if dist_squared > r*r {
+                    let cos_max = (1.0 - r*r/dist_squared).max(0.0).sqrt();
+                    1.0 / (2.0*PI * (1.0 - cos_max))
+                } else {
+                    area_to_solid_angle(t, normal.dot(dir), 4.0*PI*r*r)
+                }
+            },
+            LightShape::Triangle { p, .. } => {
+                let area = 0.5 * (p[1] - p[0]).cross(p[2] - p[0]).len();
+                area_to_solid_angle(t, normal.dot(dir), area)
+            },
+        }
+    }
+}
+
+// a uniform density over an area, seen from distance t at an angle (with cosine cos) to its normal
+fn area_to_solid_angle(t: f32, cos: f32, area: f32) -> f32 {
+    let cos = cos.abs();
+    if cos < 1e-6 || area <= 0.0 { 0.0 } else { t*t / (cos * area) }
+}
+
+// where a shadow ray should go to see a light, and what it'll see when it gets there
+pub struct LightSample {
+    pub dir: Vec3,        // unit length
+    pub dist: f32,
+    pub emitted: Color,
+    pub pdf: f32,         // of picking dir out of all the lights
+}
+
+// all the lights in a scene, picked from uniformly
+pub struct Lights {
+    lights: Vec<Light>,
+    bvh: Bvh, // for finding the ones in the way of a direction (see pdf)
+}
+
+impl Lights {
+    pub fn new(lights: Vec<Light>) -> Lights {
+        let bounds: Vec<AABoundingBox> = lights.iter().map(|l| l.bbox()).collect();
+        Lights { bvh: Bvh::from_bounds(&bounds), lights }
+    }
+
+    pub fn gather(scene: &dyn Intersectable) -> Lights {
+        let mut lights = Vec::new();
+        scene.gather_lights(&Matrix::identity(), &mut lights);
+        Lights::new(lights)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    // aim at a random light (None if there aren't any or the aim was off)
+    // TODO: pick bright and big lights more often than small dim ones
+    pub fn sample<R: Rng + ?Sized>(&self, origin: Vec3, rng: &mut R) -> Option<LightSample> {
+        if self.lights.is_empty() {
+            return None;
+        }
+        let light = &self.lights[rng.gen_range(0..self.lights.len())];
+        let dir = light.sample_dir(origin, rng);
+        let (dist, normal, uv) = light.hit(origin, dir)?;
+
+        let ray = Ray::new(origin, dir);
+        let mut hit = HitRecord::new();
+        hit.t = dist;
+        hit.point = ray.at(dist);
+        hit.front_face = normal.dot(dir) < 0.0;
+        hit.normal = if hit.front_face { normal } else { -normal };
+        hit.uv = uv;
+        hit.material = Arc::clone(&light.material);
+        let emitted = light.material.emitted(ray, &hit);
+
+        let pdf = self.pdf(origin, dir);
+        if pdf <= 0.0 || !pdf.is_finite() {
+            return None;
+        }
+        Some(LightSample { dir, dist, emitted, pdf })
+    }
+
+    // density of sample picking dir from origin (any light dir passes through could have)
+    pub fn pdf(&self, origin: Vec3, dir: Vec3) -> f32 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+        let dir = dir.normalize();
+        let mut sum = 0.0;
+        let mut hit = HitRecord::new(); // stays a miss, so traverse visits everything along the ray
+        self.bvh.traverse(Ray::new(origin, dir), &Range::default(), &mut hit, |i, _| {
+            sum += self.lights[i].pdf(origin, dir);
+            Shot::Miss
+        });
+        sum / self.lights.len() as f32
+    }
+}
+
+// the radius a sphere ends up with after m, if m keeps it a sphere
+pub fn transformed_radius(m: &Matrix, radius: f32) -> Option<f32> {
+    let x = m.apply_to_vector(Vec3::new([1.0, 0.0, 0.0]));
+    let y = m.apply_to_vector(Vec3::new([0.0, 1.0, 0.0]));
+    let z = m.apply_to_vector(Vec3::new([0.0, 0.0, 1.0]));
+    let s = x.len();
+    let tol = 1e-4 * s;
+    let uniform = (y.len() - s).abs() < tol && (z.len() - s).abs() < tol;
+    let orthogonal = x.dot(y).abs() < tol*s && y.dot(z).abs() < tol*s && z.dot(x).abs() < tol*s;
+    if uniform && orthogonal { Some(radius * s) } else { None }
+}
+
+// m flips things inside out (so triangles' windings need to be swapped to keep their fronts)
+pub fn is_mirrored(m: &Matrix) -> bool {
+    let x = m.apply_to_vector(Vec3::new([1.0, 0.0, 0.0]));
+    let y = m.apply_to_vector(Vec3::new([0.0, 1.0, 0.0]));
+    let z = m.apply_to_vector(Vec3::new([0.0, 0.0, 1.0]));
+    x.cross(y).dot(z) < 0.0
+}
+
+// a triangle (given in the space m takes to the world) as a world space light; without
+// uvs its uv is the barycentrics of p1 and p2, like Triangle's
+pub fn triangle_light(m: &Matrix, p: [Vec3; 3], uvs: Option<[Vec2; 3]>, material: &Arc<dyn Material>) -> Light {
+    let mut p = p.map(|v| m.apply_to_point(v));
+    let mut uvs = uvs.unwrap_or([Vec2::zero(), Vec2::new([1.0, 0.0]), Vec2::new([0.0, 1.0])]);
+    if is_mirrored(m) {
+        p.swap(1, 2);
+        uvs.swap(1, 2);
+    }
+    Light::triangle(p, uvs, Arc::clone(material))
+}
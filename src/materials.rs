@@ -3,19 +3,117 @@
 //
 
 use crate::*;
-use LightScatter::{ Attenuated, Absorbed };
-use rand::{thread_rng, Rng};
+use crate::settings::RenderSettings;
+use crate::textures::{Texture, SolidColor};
+use LightScatter::{ Attenuated, Specular, Absorbed };
+use rand::{Rng, RngCore};
 use std::fmt;
 
 pub enum LightScatter {
     Attenuated(Color, Ray),
+    Specular(Color, Ray), // picked some way pdf doesn't cover (a mirror-ish lobe of a layered material)
     Absorbed,
 }
 
+// the stuff between surfaces: what light travelling through it bends by and loses
+#[derive(Debug, Clone, Copy)]
+pub struct Medium {
+    pub eta: f32,
+    pub absorption: Color, // per unit distance, for each of r, g and b (Beer–Lambert)
+}
+
+impl Medium {
+    pub const fn vacuum() -> Self {
+        Self { eta: 1.0, absorption: Color::new([0.0, 0.0, 0.0]) }
+    }
+
+    // how much light makes it through dist of this
+    pub fn transmittance(&self, dist: f32) -> Color {
+        let a = &self.absorption;
+        let t = |sigma: f32| if sigma > 0.0 { (-sigma*dist).exp() } else { 1.0 };
+        Color::new([t(a[0]), t(a[1]), t(a[2])])
+    }
+}
+
+// The media a path is inside of, innermost last, so overlapping and nested things
+// (glass in water, hollow spheres) know what's on the other side of their surfaces.
+// Materials are told by hit.outer_eta.
+#[derive(Debug, Clone, Default)]
+pub struct MediumStack {
+    stack: Vec<(Arc<dyn Material>, Medium)>,
+}
+
+impl MediumStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the medium the ray is travelling through
+    pub fn current(&self) -> Medium {
+        self.stack.last().map_or(Medium::vacuum(), |m| m.1)
+    }
+
+    // what's on the other side of a surface of mat: the current medium when entering it,
+    // what's left once it's gone when leaving
+    pub fn outside_of(&self, mat: &Arc<dyn Material>, entering: bool) -> Medium {
+        if entering {
+            return self.current();
+        }
+        match self.stack.iter().rposition(|m| Arc::ptr_eq(&m.0, mat)) {
+            Some(i) => self.stack[..i].iter().chain(&self.stack[i+1..]).last().map_or(Medium::vacuum(), |m| m.1),
+            None => self.current(), // never saw it come in (e.g., the camera's inside it)
+        }
+    }
+
+    // the media after going through a surface of mat
+    pub fn crossed(&self, mat: &Arc<dyn Material>, medium: Medium, entering: bool) -> Self {
+        let mut after = self.clone();
+        if entering {
+            after.stack.push((Arc::clone(mat), medium));
+        } else if let Some(i) = after.stack.iter().rposition(|m| Arc::ptr_eq(&m.0, mat)) {
+            after.stack.remove(i);
+        }
+        after
+    }
+}
+
 // interaction of [a ray of] light with a material
-pub trait Material {
-    fn scatter(&self, ray: Ray, hit: &HitRecord, indent_by: usize) -> LightScatter;
+// (Send + Sync so hits can share them across render threads)
+pub trait Material: Send + Sync {
+    fn scatter(&self, ray: Ray, hit: &HitRecord, settings: &RenderSettings, rng: &mut dyn RngCore, indent_by: usize) -> LightScatter;
     fn log(&self) -> String;
+
+    // what's inside, for materials light can go through (the rest are opaque)
+    fn medium(&self) -> Option<Medium> {
+        None
+    }
+
+    // light given off at the hit, toward where the ray came from (most things are in the dark)
+    fn emitted(&self, ray: Ray, hit: &HitRecord) -> Color {
+        Color::black()
+    }
+
+    // the surface's color at the hit, more or less, for the albedo AOV and the denoiser
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        Color::white()
+    }
+
+    // worth aiming shadow rays at (see lights.rs)
+    fn emits(&self) -> bool {
+        false
+    }
+
+    // Light sampling needs two more things about scattering toward (unit) dir: how much
+    // of the light arriving from dir heads back along the ray (the BSDF times the cosine)...
+    fn bsdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> Color {
+        Color::black()
+    }
+
+    // ...and the density (over solid angle) of scatter picking dir. Mirror-like materials
+    // that can't be lit this way leave both alone, so a zero pdf means "don't bother."
+    fn pdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> f32 {
+        0.0
+    }
 }
 
 use core::fmt::Debug;
@@ -27,45 +125,76 @@ impl Debug for dyn Material {
 
 #[derive(Debug)]
 pub struct Lambertian {
-    pub albedo: Color,
+    pub albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
-    pub const fn new(c: Color) -> Self {
-        Self { albedo: c }
+    pub fn new(c: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(c)))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
     }
 }
 
 impl Material for Lambertian {
     fn log(&self) -> String {
-        format!("⊕ Lambertian c: {}", self.albedo)
+        format!("⊕ Lambertian c: {:?}", self.albedo)
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.albedo.value(hit.uv, hit.point)
     }
 
     // Lambertians always scatter, never absorb
-    fn scatter(&self, ray: Ray, hit: &HitRecord, indent_by: usize) -> LightScatter {
+    fn scatter(&self, ray: Ray, hit: &HitRecord, settings: &RenderSettings, rng: &mut dyn RngCore, indent_by: usize) -> LightScatter {
         let indent = vec![' '; indent_by];
         let indent: String = indent.iter().cloned().collect();
-        if DEBUG {
-            println!("{} ⊕ Lambertian.scatter: c:{}", indent_by, self.albedo);
+        if settings.debug {
+            println!("{} ⊕ Lambertian.scatter: c:{:?}", indent_by, self.albedo);
         }
 
-        let dir = random_direction(REFL_TYPE, hit.normal);
-        if DEBUG {
+        let dir = random_direction(settings.refl_type, hit.normal, rng);
+        if settings.debug {
             println!("{} reflected ray dir: {})", indent, dir);
         }
-        Attenuated(self.albedo,
+        Attenuated(self.albedo.value(hit.uv, hit.point),
                    Ray::new(hit.point, if dir.near_zero() { hit.normal } else { dir.normalize() }))
     }
+
+    // scatter's weight is always the albedo, so each reflection type effectively has the
+    // BSDF albedo*pdf/cos (only on-sphere, cosine weighted, is truly Lambertian)
+    fn bsdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> Color {
+        self.albedo.value(hit.uv, hit.point) * self.pdf(ray, hit, dir, settings)
+    }
+
+    fn pdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> f32 {
+        let cos = hit.normal.dot(dir.normalize());
+        if cos <= 0.0 {
+            return 0.0;
+        }
+        match settings.refl_type {
+            // the chord from the hit through the sphere is 2cos long, so ∫r²dr / (4π/3)
+            ReflectionType::NormalPlusPointInSphere => 2.0 * cos*cos*cos / PI,
+            ReflectionType::NormalPlusPointOnSphere => cos / PI,
+            ReflectionType::PointOnHemisphere => 1.0 / (2.0*PI),
+        }
+    }
 }
 
 pub struct Shiny {
-    pub albedo: Color,
+    pub albedo: Arc<dyn Texture>,
     pub fuzz: f32,
 }
 
 impl Shiny {
     pub fn new(c: Color, fuzziness: f32) -> Self {
-        Self { albedo: c,
+        Self::textured(Arc::new(SolidColor::new(c)), fuzziness)
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>, fuzziness: f32) -> Self {
+        Self { albedo,
                fuzz: if fuzziness > 1.0 { 1.0 } else { fuzziness },
         }
     }
@@ -73,32 +202,36 @@ impl Shiny {
 
 impl Material for Shiny {
     fn log(&self) -> String {
-        format!("⊕ Shiny c: {} fuzz:{}",
+        format!("⊕ Shiny c: {:?} fuzz:{}",
                 self.albedo, self.fuzz)
     }
 
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.albedo.value(hit.uv, hit.point)
+    }
+
     // Shinies always reflect, never absorb
-    fn scatter(&self, ray: Ray, hit: &HitRecord, indent_by: usize) -> LightScatter {
+    fn scatter(&self, ray: Ray, hit: &HitRecord, settings: &RenderSettings, rng: &mut dyn RngCore, indent_by: usize) -> LightScatter {
         let indent = vec![' '; indent_by];
         let indent: String = indent.iter().cloned().collect();
-        if DEBUG {
-            println!("{} ⊕ Shiny.scatter: c:{} fuzz:{}", indent_by, self.albedo, self.fuzz);
+        if settings.debug {
+            println!("{} ⊕ Shiny.scatter: c:{:?} fuzz:{}", indent_by, self.albedo, self.fuzz);
             println!("{}ray: {:?}", indent, ray);
             println!("{}hit: {:?}", indent, hit);
         }
 
-        let dir = ray.dir.reflect(&hit.normal) + self.fuzz*random_point_in_unit_sphere();
-        if DEBUG {
+        let dir = ray.dir.reflect(&hit.normal) + self.fuzz*random_point_in_unit_sphere(rng);
+        if settings.debug {
             println!("{}dir: {:?}", indent, dir);
             println!("{}dir.dot(hit.normal): {}", indent, dir.dot(hit.normal));
         }
         if dir.dot(hit.normal) > 0.0 {
-            if DEBUG {
+            if settings.debug {
                 println!("{} reflected ray dir: {}", indent, dir);
             }
-            return Attenuated(self.albedo, Ray::new(hit.point, dir.normalize()));
+            return Attenuated(self.albedo.value(hit.uv, hit.point), Ray::new(hit.point, dir.normalize()));
         }
-        if DEBUG {
+        if settings.debug {
             println!("{} absorbed? must've been an abnormal day", indent);
         }
         Absorbed
@@ -106,19 +239,31 @@ impl Material for Shiny {
 }
 
 pub struct Transparent {
-    pub albedo: Color,
+    pub albedo: Arc<dyn Texture>,
     pub fuzz: f32,
     pub eta: f32,
+    pub absorption: Color, // per unit distance travelled inside
 }
 
 impl Transparent {
     pub fn new(c: Color, fuzziness: f32, eta: f32) -> Self {
-        Self { albedo: c,
+        Self::textured(Arc::new(SolidColor::new(c)), fuzziness, eta)
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>, fuzziness: f32, eta: f32) -> Self {
+        Self { albedo,
                fuzz: if fuzziness > 1.0 { 1.0 } else { fuzziness },
                eta: if eta < 1.0 { 1.0 } else { eta },
+               absorption: Color::new([0.0, 0.0, 0.0]),
         }
     }
 
+    // tinted all the way through, so thick bits look darker than thin ones
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = Color::new([absorption[0].max(0.0), absorption[1].max(0.0), absorption[2].max(0.0)]);
+        self
+    }
+
     // Use Schlick's approximation for reflectance
     fn reflectance(&self, cos_theta: f32, src_eta: f32, dst_eta: f32) -> f32 {
         let mut r0 = (src_eta - dst_eta) / (src_eta + dst_eta);
@@ -129,37 +274,46 @@ impl Transparent {
 
 impl Material for Transparent {
     fn log(&self) -> String{
-        format!("⊕ Transparent c: {} η:{} fuzz:{}",
-                self.albedo, self.eta, self.fuzz)
+        format!("⊕ Transparent c: {:?} η:{} fuzz:{} absorb:{}",
+                self.albedo, self.eta, self.fuzz, self.absorption)
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.albedo.value(hit.uv, hit.point)
+    }
+
+    fn medium(&self) -> Option<Medium> {
+        Some(Medium { eta: self.eta, absorption: self.absorption })
     }
 
     // reflect or refract, just pick one
-    fn scatter(&self, ray: Ray, hit: &HitRecord, indent_by: usize) -> LightScatter {
+    fn scatter(&self, ray: Ray, hit: &HitRecord, settings: &RenderSettings, rng: &mut dyn RngCore, indent_by: usize) -> LightScatter {
         let indent = vec![' '; indent_by];
         let indent: String = indent.iter().cloned().collect();
-        if DEBUG {
-            println!("{} ⊕ Transparent.scatter of ray: {} at hit: {} using my c:{} η:{} fuzz:{}",
+        if settings.debug {
+            println!("{} ⊕ Transparent.scatter of ray: {} at hit: {} using my c:{:?} η:{} fuzz:{}",
                      indent, ray, hit, self.albedo, self.eta, self.fuzz);
         }
 
-        // FIXME: add previous material's eta to hit record (assume 1.0 for now)
-        let src_eta = if hit.front_face { 1.0 } else { self.eta };
-        let dst_eta = if hit.front_face { self.eta } else { 1.0 };
+        // whatever's on the other side (glass in water, the hollow in a bubble) is in the hit record
+        let src_eta = if hit.front_face { hit.outer_eta } else { self.eta };
+        let dst_eta = if hit.front_face { self.eta } else { hit.outer_eta };
         let refraction_ratio = src_eta / dst_eta;
+        let ray = Ray::new(ray.origin, ray.dir.normalize()); // (the angles below need it)
         let cos_theta = (-1.0*hit.normal.dot(ray.dir)).min(1.0); // *-1.0 so both in same direction
         let sin_theta = (1.0 - cos_theta*cos_theta).sqrt();
         let reflect = refraction_ratio * sin_theta > 1.0
-            || self.reflectance(cos_theta, src_eta, dst_eta) > rand::thread_rng().gen();
+            || self.reflectance(cos_theta, src_eta, dst_eta) > rng.gen();
 
         if reflect {
-            let dir = ray.dir.reflect(&hit.normal) + self.fuzz*random_point_in_unit_sphere();
-            if DEBUG {
+            let dir = ray.dir.reflect(&hit.normal) + self.fuzz*random_point_in_unit_sphere(rng);
+            if settings.debug {
                 println!("{} reflected. ray dir: {}", indent, dir);
             }
-            return Attenuated(self.albedo, Ray::new(hit.point, dir.normalize()));
+            return Attenuated(self.albedo.value(hit.uv, hit.point), Ray::new(hit.point, dir.normalize()));
         }
         else {
-            if DEBUG {
+            if settings.debug {
                 println!("\trefract this vector: {}", ray.dir);
                 println!("\tfrom material with etai: {} to etat: {}", src_eta, dst_eta);
                 println!("\thit normal: {}", hit.normal);
@@ -169,13 +323,57 @@ impl Material for Transparent {
             }
 
             let dir = ray.dir.refract(hit.normal, refraction_ratio, cos_theta)
-                + self.fuzz*random_point_in_unit_sphere(); // TODO: give fuzzy refraction diff fuzz than reflections
-            if DEBUG {
+                + self.fuzz*random_point_in_unit_sphere(rng); // TODO: give fuzzy refraction diff fuzz than reflections
+            if settings.debug {
                 println!("{} refracted. ray dir: {}", indent, dir.normalize());
             }
-            // FIXME: add some albedo for how long (distance) it spent in the previous material
-            // FIXME: set hit.eta when it goes through rather than assuming 1.0
-            return Attenuated(self.albedo, Ray::new(hit.point, dir));
+            // (absorption on the way through is up to the integrator, see ray_color)
+            return Attenuated(self.albedo.value(hit.uv, hit.point), Ray::new(hit.point, dir));
         }
     }
 }
+
+// glows, doesn't reflect
+pub struct DiffuseLight {
+    pub emit: Arc<dyn Texture>,
+    pub two_sided: bool, // else only the front face (the side the normal points to) glows
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn textured(emit: Arc<dyn Texture>) -> Self {
+        Self { emit, two_sided: false }
+    }
+
+    pub fn two_sided(mut self) -> Self {
+        self.two_sided = true;
+        self
+    }
+}
+
+impl Material for DiffuseLight {
+    fn log(&self) -> String {
+        format!("⊕ DiffuseLight c: {:?}{}", self.emit, if self.two_sided { " (two sided)" } else { "" })
+    }
+
+    fn scatter(&self, ray: Ray, hit: &HitRecord, settings: &RenderSettings, rng: &mut dyn RngCore, indent_by: usize) -> LightScatter {
+        Absorbed
+    }
+
+    fn emitted(&self, ray: Ray, hit: &HitRecord) -> Color {
+        if hit.front_face || self.two_sided { self.emit.value(hit.uv, hit.point) } else { Color::black() }
+    }
+
+    fn emits(&self) -> bool {
+        true // (well, unless it's a black texture)
+    }
+
+    // (its glow, as a color)
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        let c = self.emit.value(hit.uv, hit.point);
+        Color::new([c[0].clamp(0.0, 1.0), c[1].clamp(0.0, 1.0), c[2].clamp(0.0, 1.0)])
+    }
+}
@@ -0,0 +1,243 @@
+//
+// triangles, alone and in meshes
+//
+
+use crate::*;
+use crate::bvh::Bvh;
+use crate::lights::{Light, triangle_light};
+use std::fmt;
+
+// Watertight ray/triangle intersection (Woop, Benthin and Wald, JCGT 2013): the
+// ray is sheared so it points down +z, then edges are tested in 2d, so rays
+// through a shared edge or vertex hit exactly one of the triangles that share it.
+// Returns t and the barycentric weights of p0, p1 and p2 if hit inside [rng.min, tmax].
+pub fn intersect_triangle(ray: &Ray, p0: Vec3, p1: Vec3, p2: Vec3,
+                          rng: &Range, tmax: f32) -> Option<(f32, Vec3)> {
+    // permute so z is the ray's largest dimension (and keep the winding)
+    let d = ray.dir;
+    let kz = if d.x().abs() > d.y().abs() {
+        if d.x().abs() > d.z().abs() { 0 } else { 2 }
+    } else if d.y().abs() > d.z().abs() { 1 } else { 2 };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if d[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // shear
+    let sx = d[kx] / d[kz];
+    let sy = d[ky] / d[kz];
+    let sz = 1.0 / d[kz];
+    let a = p0 - ray.origin;
+    let b = p1 - ray.origin;
+    let c = p2 - ray.origin;
+    let (ax, ay) = (a[kx] - sx*a[kz], a[ky] - sy*a[kz]);
+    let (bx, by) = (b[kx] - sx*b[kz], b[ky] - sy*b[kz]);
+    let (cx, cy) = (c[kx] - sx*c[kz], c[ky] - sy*c[kz]);
+
+    // scaled barycentrics (edge functions), redone in double precision when on an edge
+    let mut u = cx*by - cy*bx;
+    let mut v = ax*cy - ay*cx;
+    let mut w = bx*ay - by*ax;
+    if u == 0.0 || v == 0.0 || w == 0.0 {
+        u = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+        v = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+        w = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+    }
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    // scaled hit distance
+    let (az, bz, cz) = (sz*a[kz], sz*b[kz], sz*c[kz]);
+    let t = (u*az + v*bz + w*cz) / det;
+    if t.outside(rng) || t > tmax {
+        return None;
+    }
+    Some((t, Vec3::new([u/det, v/det, w/det])))
+}
+
+// fill in the hit for a triangle hit at t with barycentric weights bary
+fn set_hit(hit: &mut HitRecord, ray: &Ray, t: f32, bary: Vec3, p: [Vec3; 3],
+           normals: Option<[Vec3; 3]>, uvs: Option<[Vec2; 3]>, material: &Arc<dyn Material>) {
+    let geometric = (p[1] - p[0]).cross(p[2] - p[0]).normalize();
+    hit.t = t;
+    hit.point = bary[0]*p[0] + bary[1]*p[1] + bary[2]*p[2];
+    hit.front_face = geometric.dot(ray.dir) < 0.0;
+
+    // the shading normal can wander, but keep it on the same side as the geometry
+    let normal = match normals {
+        Some(n) => {
+            let n = bary[0]*n[0] + bary[1]*n[1] + bary[2]*n[2];
+            if n.near_zero() { geometric } else if n.dot(geometric) < 0.0 { -n.normalize() } else { n.normalize() }
+        },
+        None => geometric,
+    };
+    hit.normal = if hit.front_face { normal } else { -normal };
+
+    hit.uv = match uvs {
+        Some(uv) => bary[0]*uv[0] + bary[1]*uv[1] + bary[2]*uv[2],
+        None => Vec2::new([bary[1], bary[2]]),
+    };
+    hit.bary = bary;
+    hit.material = Arc::clone(material);
+}
+
+fn triangle_bbox(p: &[Vec3; 3]) -> AABoundingBox {
+    AABoundingBox::new(p[0], p[0]).grow(p[1]).grow(p[2])
+}
+
+#[derive(Debug)]
+pub struct Triangle {
+    pub p: [Vec3; 3],
+    pub normals: Option<[Vec3; 3]>, // per-vertex (for smooth shading), else the flat face normal
+    pub uvs: Option<[Vec2; 3]>,     // per-vertex, else uv is the barycentrics of p1 and p2
+    pub material: Arc<dyn Material>,
+}
+
+impl fmt::Display for Triangle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "◺ {} {} {}", self.p[0], self.p[1], self.p[2])
+    }
+}
+
+impl Triangle {
+    pub fn new(p0: Vec3, p1: Vec3, p2: Vec3, mat: Arc<dyn Material>) -> Triangle {
+        Triangle { p: [p0, p1, p2], normals: None, uvs: None, material: mat }
+    }
+
+    pub fn with_normals(mut self, n0: Vec3, n1: Vec3, n2: Vec3) -> Triangle {
+        self.normals = Some([n0, n1, n2]);
+        self
+    }
+
+    pub fn with_uvs(mut self, uv0: Vec2, uv1: Vec2, uv2: Vec2) -> Triangle {
+        self.uvs = Some([uv0, uv1, uv2]);
+        self
+    }
+}
+
+impl Intersectable for Triangle {
+    fn intersect(&self, ray: Ray, rng: &Range, hit: &mut HitRecord, indent_by: usize) -> Shot {
+        if debug() {
+            let indent: String = vec![' '; indent_by].iter().cloned().collect();
+            println!("{}{}", indent, self);
+        }
+        match intersect_triangle(&ray, self.p[0], self.p[1], self.p[2], rng, hit.t) {
+            Some((t, bary)) => {
+                set_hit(hit, &ray, t, bary, self.p, self.normals, self.uvs, &self.material);
+                Shot::Hit
+            },
+            None => Shot::Miss,
+        }
+    }
+
+    fn bbox(&self) -> AABoundingBox {
+        triangle_bbox(&self.p)
+    }
+
+    fn gather_lights(&self, to_world: &Matrix, lights: &mut Vec<Light>) {
+        if self.material.emits() {
+            lights.push(triangle_light(to_world, self.p, self.uvs, &self.material));
+        }
+    }
+}
+
+// Vertex data for one or more meshes. Normals and uvs, when given, go with the
+// position of the same index.
+#[derive(Debug, Default)]
+pub struct MeshVertices {
+    pub positions: Vec<Vec3>,
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<Vec2>>,
+}
+
+// a bunch of triangles indexing into (shared) vertices, with a bvh of their own
+pub struct TriangleMesh {
+    verts: Arc<MeshVertices>,
+    indices: Vec<[u32; 3]>,
+    pub material: Arc<dyn Material>,
+    bvh: Bvh,
+}
+
+impl fmt::Display for TriangleMesh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "◭ {} triangles, {} verts", self.indices.len(), self.verts.positions.len())
+    }
+}
+
+impl TriangleMesh {
+    pub fn new(verts: Arc<MeshVertices>, indices: Vec<[u32; 3]>, mat: Arc<dyn Material>) -> TriangleMesh {
+        let n = verts.positions.len();
+        assert!(verts.normals.as_ref().is_none_or(|v| v.len() == n), "need one normal per position");
+        assert!(verts.uvs.as_ref().is_none_or(|v| v.len() == n), "need one uv per position");
+        assert!(indices.iter().flatten().all(|&i| (i as usize) < n), "mesh index out of range");
+
+        let mut mesh = TriangleMesh { verts, indices, material: mat, bvh: Bvh::from_bounds(&[]) };
+        let bounds: Vec<AABoundingBox> = (0..mesh.indices.len()).map(|i| triangle_bbox(&mesh.points(i))).collect();
+        mesh.bvh = Bvh::from_bounds(&bounds);
+        mesh
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn vertices(&self) -> &Arc<MeshVertices> {
+        &self.verts
+    }
+
+    // the three points of triangle i
+    pub fn points(&self, i: usize) -> [Vec3; 3] {
+        let [a, b, c] = self.indices[i];
+        let p = &self.verts.positions;
+        [p[a as usize], p[b as usize], p[c as usize]]
+    }
+
+    fn normals(&self, i: usize) -> Option<[Vec3; 3]> {
+        let [a, b, c] = self.indices[i];
+        self.verts.normals.as_ref().map(|n| [n[a as usize], n[b as usize], n[c as usize]])
+    }
+
+    fn uvs(&self, i: usize) -> Option<[Vec2; 3]> {
+        let [a, b, c] = self.indices[i];
+        self.verts.uvs.as_ref().map(|uv| [uv[a as usize], uv[b as usize], uv[c as usize]])
+    }
+}
+
+impl Intersectable for TriangleMesh {
+    fn intersect(&self, ray: Ray, rng: &Range, hit: &mut HitRecord, indent_by: usize) -> Shot {
+        if debug() {
+            let indent: String = vec![' '; indent_by].iter().cloned().collect();
+            println!("{}{}", indent, self);
+        }
+        self.bvh.traverse(ray, rng, hit, |i, hit| {
+            let p = self.points(i);
+            match intersect_triangle(&ray, p[0], p[1], p[2], rng, hit.t) {
+                Some((t, bary)) => {
+                    set_hit(hit, &ray, t, bary, p, self.normals(i), self.uvs(i), &self.material);
+                    Shot::Hit
+                },
+                None => Shot::Miss,
+            }
+        })
+    }
+
+    fn bbox(&self) -> AABoundingBox {
+        self.bvh.bbox()
+    }
+
+    fn gather_lights(&self, to_world: &Matrix, lights: &mut Vec<Light>) {
+        if self.material.emits() {
+            lights.extend((0..self.len()).map(|i| triangle_light(to_world, self.points(i), self.uvs(i), &self.material)));
+        }
+    }
+}
@@ -0,0 +1,381 @@
+//
+// microfacet materials: rough metals and rough glass
+//
+// Surfaces made of lots of tiny mirrors facing every which way, how many of them
+// facing which way given by the GGX (Trowbridge-Reitz) distribution. Scattering picks
+// one of the mirrors the ray can actually see (Heitz, "Sampling the GGX Distribution
+// of Visible Normals", JCGT 2018), and everything is worked out in a frame where the
+// hit normal is +z. See Walter et al., "Microfacet Models for Refraction through
+// Rough Surfaces" (EGSR 2007) for where the formulas come from.
+//
+
+use crate::*;
+use crate::materials::{LightScatter, Medium};
+use crate::settings::RenderSettings;
+use LightScatter::{ Attenuated, Absorbed };
+use rand::{Rng, RngCore};
+
+// below this alpha a surface is treated as perfectly smooth (the formulas get twitchy)
+const SMOOTH_ALPHA: f32 = 1e-3;
+
+// the hit's shading frame: normal is z
+struct Frame {
+    t: Vec3,
+    b: Vec3,
+    n: Vec3,
+}
+
+impl Frame {
+    fn new(n: Vec3) -> Self {
+        let (t, b) = orthonormal_basis(n);
+        Frame { t, b, n }
+    }
+
+    fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new([v.dot(self.t), v.dot(self.b), v.dot(self.n)])
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        v.x()*self.t + v.y()*self.b + v.z()*self.n
+    }
+}
+
+// GGX with alpha = roughness² (so roughness feels more linear)
+#[derive(Debug, Clone, Copy)]
+struct Ggx {
+    alpha: f32,
+}
+
+impl Ggx {
+    fn new(roughness: f32) -> Self {
+        let r = roughness.clamp(0.0, 1.0);
+        Ggx { alpha: r*r }
+    }
+
+    fn is_smooth(&self) -> bool {
+        self.alpha < SMOOTH_ALPHA
+    }
+
+    // density of microfacet normals h
+    fn d(&self, h: Vec3) -> f32 {
+        if h.z() <= 0.0 {
+            return 0.0;
+        }
+        let a2 = self.alpha * self.alpha;
+        let t = h.z()*h.z() * (a2 - 1.0) + 1.0;
+        a2 / (PI * t*t)
+    }
+
+    // Smith's Λ: how much of the surface is hidden seen from v
+    fn lambda(&self, v: Vec3) -> f32 {
+        let cos2 = v.z()*v.z();
+        if cos2 <= 0.0 {
+            return f32::INFINITY;
+        }
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        0.5 * (-1.0 + (1.0 + self.alpha*self.alpha*tan2).sqrt())
+    }
+
+    // fraction of microfacets visible from v
+    fn g1(&self, v: Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(v))
+    }
+
+    // fraction visible from both (height correlated)
+    fn g2(&self, wo: Vec3, wi: Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // density of picking h with sample_visible, seen from wo
+    fn visible_pdf(&self, wo: Vec3, h: Vec3) -> f32 {
+        let cos_o = wo.z();
+        if cos_o <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(h).max(0.0) * self.d(h) / cos_o
+    }
+
+    // a microfacet normal visible from wo (wo.z > 0)
+    fn sample_visible<R: Rng + ?Sized>(&self, wo: Vec3, rng: &mut R) -> Vec3 {
+        let a = self.alpha;
+        // stretch so the distribution is a hemisphere, pick a point on the part wo sees...
+        let vh = Vec3::new([a*wo.x(), a*wo.y(), wo.z()]).normalize();
+        let len2 = vh.x()*vh.x() + vh.y()*vh.y();
+        let t1 = if len2 > 0.0 { Vec3::new([-vh.y(), vh.x(), 0.0]) / len2.sqrt() } else { Vec3::new([1.0, 0.0, 0.0]) };
+        let t2 = vh.cross(t1);
+        let r = rng.gen::<f32>().sqrt();
+        let phi = 2.0*PI * rng.gen::<f32>();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z());
+        let p2 = (1.0 - s) * (1.0 - p1*p1).max(0.0).sqrt() + s * r * phi.sin();
+        let nh = p1*t1 + p2*t2 + (1.0 - p1*p1 - p2*p2).max(0.0).sqrt()*vh;
+        // ...and unstretch
+        Vec3::new([a*nh.x(), a*nh.y(), nh.z().max(1e-6)]).normalize()
+    }
+}
+
+fn reflect(wo: Vec3, h: Vec3) -> Vec3 {
+    2.0*wo.dot(h)*h - wo
+}
+
+// direction wo refracts to through a surface with normal h (on wo's side), None if it
+// reflects totally. eta is the ratio of wo's side to the other.
+fn refract(wo: Vec3, h: Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = wo.dot(h);
+    let sin2_t = eta*eta * (1.0 - cos_i*cos_i).max(0.0);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some((-eta*wo + (eta*cos_i - cos_t)*h).normalize())
+}
+
+// unpolarized reflectance from eta_i into eta_t (exact, unlike Schlick's)
+pub fn fresnel_dielectric(cos_i: f32, eta_i: f32, eta_t: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin_t = eta_i / eta_t * (1.0 - cos_i*cos_i).max(0.0).sqrt();
+    if sin_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin_t*sin_t).max(0.0).sqrt();
+    let parallel = (eta_t*cos_i - eta_i*cos_t) / (eta_t*cos_i + eta_i*cos_t);
+    let perpendicular = (eta_i*cos_i - eta_t*cos_t) / (eta_i*cos_i + eta_t*cos_t);
+    0.5 * (parallel*parallel + perpendicular*perpendicular)
+}
+
+// unpolarized reflectance of a metal with complex index of refraction eta + ik
+// (both relative to the outside)
+pub fn fresnel_conductor(cos_i: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let t0 = eta*eta - k*k - sin2;
+    let a2b2 = (t0*t0 + 4.0*eta*eta*k*k).max(0.0).sqrt();
+    let t1 = a2b2 + cos2;
+    let a = (0.5 * (a2b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * a * cos2.sqrt();
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2*a2b2 + sin2*sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rp + rs)
+}
+
+//
+// rough metal
+//
+
+pub struct Conductor {
+    pub eta: Color, // real part of the index of refraction, per channel
+    pub k: Color,   // imaginary part (absorption)
+    pub roughness: f32,
+}
+
+impl Conductor {
+    pub fn new(eta: Color, k: Color, roughness: f32) -> Self {
+        Self { eta, k, roughness: roughness.clamp(0.0, 1.0) }
+    }
+
+    // (rgb-ish values of measured indices, sampled around 650, 550 and 450nm)
+    pub fn gold(roughness: f32) -> Self {
+        Self::new(Color::new([0.143, 0.374, 1.442]), Color::new([3.983, 2.385, 1.603]), roughness)
+    }
+
+    pub fn copper(roughness: f32) -> Self {
+        Self::new(Color::new([0.200, 0.924, 1.102]), Color::new([3.912, 2.452, 2.142]), roughness)
+    }
+
+    pub fn aluminium(roughness: f32) -> Self {
+        Self::new(Color::new([1.657, 0.880, 0.521]), Color::new([9.224, 6.270, 4.837]), roughness)
+    }
+
+    pub fn preset(name: &str, roughness: f32) -> Option<Self> {
+        match name {
+            "gold" => Some(Self::gold(roughness)),
+            "copper" => Some(Self::copper(roughness)),
+            "aluminium" | "aluminum" => Some(Self::aluminium(roughness)),
+            _ => None,
+        }
+    }
+
+    fn fresnel(&self, cos: f32, outer_eta: f32) -> Color {
+        let f = |c: usize| fresnel_conductor(cos, self.eta[c] / outer_eta, self.k[c] / outer_eta);
+        Color::new([f(0), f(1), f(2)])
+    }
+}
+
+impl Material for Conductor {
+    fn log(&self) -> String {
+        format!("⊕ Conductor η:{} k:{} roughness:{}", self.eta, self.k, self.roughness)
+    }
+
+    // what it reflects head on
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.fresnel(1.0, 1.0)
+    }
+
+    fn scatter(&self, ray: Ray, hit: &HitRecord, settings: &RenderSettings, rng: &mut dyn RngCore, indent_by: usize) -> LightScatter {
+        let frame = Frame::new(hit.normal);
+        let wo = frame.to_local(-ray.dir.normalize());
+        if wo.z() <= 0.0 {
+            return Absorbed; // (an interpolated normal facing away)
+        }
+        let ggx = Ggx::new(self.roughness);
+        let h = if ggx.is_smooth() { Vec3::new([0.0, 0.0, 1.0]) } else { ggx.sample_visible(wo, rng) };
+        let wi = reflect(wo, h);
+        if wi.z() <= 0.0 {
+            return Absorbed; // bounced off the back of a bump
+        }
+        // bsdf*cos/pdf, most of which cancels
+        let weight = if ggx.is_smooth() { 1.0 } else { ggx.g2(wo, wi) / ggx.g1(wo) };
+        if settings.debug {
+            let indent: String = vec![' '; indent_by].iter().collect();
+            println!("{} ⊕ Conductor.scatter: h:{} wi:{} weight:{}", indent, h, wi, weight);
+        }
+        Attenuated(weight * self.fresnel(wo.dot(h), hit.outer_eta), Ray::new(hit.point, frame.to_world(wi)))
+    }
+
+    fn bsdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> Color {
+        let ggx = Ggx::new(self.roughness);
+        let frame = Frame::new(hit.normal);
+        let (wo, wi) = (frame.to_local(-ray.dir.normalize()), frame.to_local(dir));
+        if ggx.is_smooth() || wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Color::black();
+        }
+        let h = (wo + wi).normalize();
+        (ggx.d(h) * ggx.g2(wo, wi) / (4.0 * wo.z())) * self.fresnel(wo.dot(h), hit.outer_eta)
+    }
+
+    fn pdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> f32 {
+        let ggx = Ggx::new(self.roughness);
+        let frame = Frame::new(hit.normal);
+        let (wo, wi) = (frame.to_local(-ray.dir.normalize()), frame.to_local(dir));
+        if ggx.is_smooth() || wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalize();
+        ggx.visible_pdf(wo, h) / (4.0 * wo.dot(h))
+    }
+}
+
+//
+// rough glass
+//
+
+pub struct Dielectric {
+    pub eta: f32,
+    pub roughness: f32,
+    pub absorption: Color, // per unit distance travelled inside (see Medium)
+}
+
+impl Dielectric {
+    pub fn new(eta: f32, roughness: f32) -> Self {
+        Self { eta: eta.max(1.0), roughness: roughness.clamp(0.0, 1.0), absorption: Color::new([0.0, 0.0, 0.0]) }
+    }
+
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = Color::new([absorption[0].max(0.0), absorption[1].max(0.0), absorption[2].max(0.0)]);
+        self
+    }
+
+    // etas of the side the ray came from and the other one
+    fn etas(&self, hit: &HitRecord) -> (f32, f32) {
+        if hit.front_face { (hit.outer_eta, self.eta) } else { (self.eta, hit.outer_eta) }
+    }
+
+    // the half vector (on wo's side) for a pair of directions, and whether it's a reflection
+    fn half_vector(wo: Vec3, wi: Vec3, eta_o: f32, eta_t: f32) -> Option<(Vec3, bool)> {
+        let reflected = wi.z() > 0.0;
+        let h = if reflected { wo + wi } else { -(eta_o*wo + eta_t*wi) };
+        if h.near_zero() {
+            return None;
+        }
+        let h = h.normalize();
+        let h = if h.z() < 0.0 { -h } else { h };
+        // both have to agree with h about which side they're on
+        if wo.dot(h) <= 0.0 || (wi.dot(h) > 0.0) != reflected {
+            return None;
+        }
+        Some((h, reflected))
+    }
+}
+
+impl Material for Dielectric {
+    fn log(&self) -> String {
+        format!("⊕ Dielectric η:{} roughness:{} absorb:{}", self.eta, self.roughness, self.absorption)
+    }
+
+    fn medium(&self) -> Option<Medium> {
+        Some(Medium { eta: self.eta, absorption: self.absorption })
+    }
+
+    fn scatter(&self, ray: Ray, hit: &HitRecord, settings: &RenderSettings, rng: &mut dyn RngCore, indent_by: usize) -> LightScatter {
+        let frame = Frame::new(hit.normal);
+        let wo = frame.to_local(-ray.dir.normalize());
+        if wo.z() <= 0.0 {
+            return Absorbed;
+        }
+        let (eta_o, eta_t) = self.etas(hit);
+        let ggx = Ggx::new(self.roughness);
+        let h = if ggx.is_smooth() { Vec3::new([0.0, 0.0, 1.0]) } else { ggx.sample_visible(wo, rng) };
+
+        // reflect or refract in proportion to the fresnel reflectance (so it cancels out)
+        let f = fresnel_dielectric(wo.dot(h), eta_o, eta_t);
+        let wi = if rng.gen::<f32>() < f { Some(reflect(wo, h)) } else { refract(wo, h, eta_o / eta_t) };
+        let wi = match wi {
+            Some(wi) if (wi.z() > 0.0) == (wi.dot(h) > 0.0) && wi.z() != 0.0 => wi,
+            _ => return Absorbed, // went off the wrong side of a bump
+        };
+        let weight = if ggx.is_smooth() { 1.0 } else { ggx.g2(wo, wi) / ggx.g1(wo) };
+        if settings.debug {
+            let indent: String = vec![' '; indent_by].iter().collect();
+            println!("{} ⊕ Dielectric.scatter: η {} -> {} h:{} wi:{} weight:{}", indent, eta_o, eta_t, h, wi, weight);
+        }
+        Attenuated(Color::new([weight; 3]), Ray::new(hit.point, frame.to_world(wi)))
+    }
+
+    // (no η² radiance scaling when refracting, to match Transparent)
+    fn bsdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> Color {
+        let ggx = Ggx::new(self.roughness);
+        let frame = Frame::new(hit.normal);
+        let (wo, wi) = (frame.to_local(-ray.dir.normalize()), frame.to_local(dir));
+        let (eta_o, eta_t) = self.etas(hit);
+        if ggx.is_smooth() || wo.z() <= 0.0 {
+            return Color::black();
+        }
+        let (h, reflected) = match Self::half_vector(wo, wi, eta_o, eta_t) {
+            Some(hr) => hr,
+            None => return Color::black(),
+        };
+        let f = fresnel_dielectric(wo.dot(h), eta_o, eta_t);
+        let dg = ggx.d(h) * ggx.g2(wo, wi);
+        let value = if reflected {
+            f * dg / (4.0 * wo.z())
+        } else {
+            let denom = eta_o*wo.dot(h) + eta_t*wi.dot(h);
+            (1.0 - f) * dg * wo.dot(h) * eta_t*eta_t * wi.dot(h).abs() / (wo.z() * denom*denom)
+        };
+        Color::new([value; 3])
+    }
+
+    fn pdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> f32 {
+        let ggx = Ggx::new(self.roughness);
+        let frame = Frame::new(hit.normal);
+        let (wo, wi) = (frame.to_local(-ray.dir.normalize()), frame.to_local(dir));
+        let (eta_o, eta_t) = self.etas(hit);
+        if ggx.is_smooth() || wo.z() <= 0.0 {
+            return 0.0;
+        }
+        let (h, reflected) = match Self::half_vector(wo, wi, eta_o, eta_t) {
+            Some(hr) => hr,
+            None => return 0.0,
+        };
+        let f = fresnel_dielectric(wo.dot(h), eta_o, eta_t);
+        let pdf_h = ggx.visible_pdf(wo, h);
+        if reflected {
+            f * pdf_h / (4.0 * wo.dot(h))
+        } else {
+            let denom = eta_o*wo.dot(h) + eta_t*wi.dot(h);
+            (1.0 - f) * pdf_h * eta_t*eta_t * wi.dot(h).abs() / (denom*denom)
+        }
+    }
+}
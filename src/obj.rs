@@ -0,0 +1,371 @@
+//
+// Wavefront .obj (and .mtl) import
+//
+// Reads positions (v), texcoords (vt), normals (vn) and polygon faces (f, fan
+// triangulated), with groups and objects (g, o) becoming nested Jumbles and
+// materials (mtllib, usemtl) mapped onto ours:
+//
+//   Ke not black                      -> DiffuseLight(Ke)
+//   d < 1, Tr > 0 or illum 4, 6, 7, 9 -> Transparent(Tf or Kd, fuzz from Ns, eta from Ni)
+//   illum 3, or Ks brighter than Kd  -> Shiny(Ks, fuzz from Ns)
+//   anything else                    -> Lambertian(Kd)
+//
+// with map_Kd (a png) standing in for Kd. Everything in a file shares one vertex
+// buffer; each group gets a TriangleMesh per material it uses. Unknown statements
+// (s, l, map_Ks, ...) are skipped.
+//
+
+use crate::*;
+use crate::materials::*;
+use crate::scenefile::SceneError;
+use crate::textures::{Texture, SolidColor, ImageTexture};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// every file read along the way (the obj, its mtls and their textures) gets added to files
+pub fn load_obj(path: &str, files: &mut Vec<PathBuf>) -> Result<Jumble, SceneError> {
+    let src = read(path)?;
+    files.push(PathBuf::from(path));
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let name = Path::new(path).file_stem().map_or("obj".to_string(), |s| s.to_string_lossy().to_string());
+    parse_obj(&src, dir, &name, files).map_err(|e| located(e, path))
+}
+
+fn read(path: &str) -> Result<String, SceneError> {
+    std::fs::read_to_string(path).map_err(|e| SceneError { line: 0, col: 0, msg: format!("can't read {}: {}", path, e) })
+}
+
+// errors from this file get its name attached (since obj and mtl files can be anywhere)
+fn located(e: SceneError, path: &str) -> SceneError {
+    if e.line == 0 { e } else { SceneError { line: 0, col: 0, msg: e.in_file(path) } }
+}
+
+// split a line into (column, word) pairs, ignoring comments
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let line = line.split('#').next().unwrap_or("");
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => { words.push((line[..s].chars().count() + 1, &line[s..i])); start = None; },
+            (false, None) => start = Some(i),
+            _ => {},
+        }
+    }
+    words
+}
+
+// the statement's arguments as numbers
+fn numbers(line: usize, args: &[(usize, &str)], min: usize, max: usize, what: &str) -> Result<Vec<f32>, SceneError> {
+    if args.len() < min || args.len() > max {
+        let col = args.first().map_or(1, |a| a.0);
+        let count = if min == max { format!("{}", min) } else { format!("{} to {}", min, max) };
+        return Err(SceneError { line, col, msg: format!("{} needs {} numbers, found {}", what, count, args.len()) });
+    }
+    args.iter().map(|&(col, w)| match w.parse::<f32>() {
+        Ok(n) if n.is_finite() => Ok(n),
+        _ => Err(SceneError { line, col, msg: format!("expected a number for {}, found '{}'", what, w) }),
+    }).collect()
+}
+
+//
+// mtl
+//
+
+struct MtlDesc {
+    kd: Color,
+    map_kd: Option<Arc<dyn Texture>>,
+    ks: Color,
+    ke: Color,
+    tf: Option<Color>,
+    ns: f32,
+    ni: f32,
+    d: f32,
+    illum: u32,
+}
+
+impl Default for MtlDesc {
+    fn default() -> Self {
+        MtlDesc { kd: Color::new([0.8, 0.8, 0.8]), map_kd: None, ks: Color::black(), ke: Color::black(), tf: None, ns: 0.0, ni: 1.5, d: 1.0, illum: 1 }
+    }
+}
+
+impl MtlDesc {
+    fn to_material(&self) -> Arc<dyn Material> {
+        // Phong exponent to roughness (the usual alpha = sqrt(2 / (n + 2)))
+        let fuzz = (2.0 / (self.ns.max(0.0) + 2.0)).sqrt().min(1.0);
+        let brightest = |c: &Color| c[0].max(c[1]).max(c[2]);
+        let diffuse = || -> Arc<dyn Texture> {
+            match &self.map_kd {
+                Some(tex) => Arc::clone(tex),
+                None => Arc::new(SolidColor::new(self.kd)),
+            }
+        };
+
+        if brightest(&self.ke) > 0.0 {
+            Arc::new(DiffuseLight::new(self.ke))
+        } else if self.d < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            let eta = if self.ni >= 1.0 { self.ni } else { 1.5 };
+            // a perfectly smooth glass is more likely than one with an Ns of 0
+            let fuzz = if self.ns > 0.0 { fuzz } else { 0.0 };
+            match self.tf {
+                Some(tf) => Arc::new(Transparent::new(tf, fuzz, eta)),
+                None => Arc::new(Transparent::textured(diffuse(), fuzz, eta)),
+            }
+        } else if self.illum == 3 || brightest(&self.ks) > brightest(&self.kd) {
+            Arc::new(Shiny::new(self.ks, fuzz))
+        } else {
+            Arc::new(Lambertian::textured(diffuse()))
+        }
+    }
+}
+
+fn load_mtl(path: &Path, materials: &mut HashMap<String, Arc<dyn Material>>, files: &mut Vec<PathBuf>) -> Result<(), SceneError> {
+    let path = path.to_string_lossy().to_string();
+    let src = read(&path)?;
+    files.push(PathBuf::from(&path));
+    let dir = Path::new(&path).parent().unwrap_or_else(|| Path::new(""));
+    parse_mtl(&src, dir, materials, files).map_err(|e| located(e, &path))
+}
+
+fn parse_mtl(src: &str, dir: &Path, materials: &mut HashMap<String, Arc<dyn Material>>,
+             files: &mut Vec<PathBuf>) -> Result<(), SceneError> {
+    let mut current: Option<(String, MtlDesc)> = None;
+    for (l, text) in src.lines().enumerate() {
+        let line = l + 1;
+        let words = words(text);
+        let (col, key) = match words.first() {
+            Some(&w) => w,
+            None => continue,
+        };
+        let args = &words[1..];
+        if key == "newmtl" {
+            if let Some((name, desc)) = current.take() {
+                materials.insert(name, desc.to_material());
+            }
+            let name = args.iter().map(|a| a.1).collect::<Vec<_>>().join(" ");
+            if name.is_empty() {
+                return Err(SceneError { line, col, msg: "newmtl needs a name".to_string() });
+            }
+            current = Some((name, MtlDesc::default()));
+            continue;
+        }
+        let desc = match current.as_mut() {
+            Some((_, desc)) => desc,
+            None if matches!(key, "Kd" | "Ks" | "Ke" | "Tf" | "Ns" | "Ni" | "d" | "Tr" | "illum") => {
+                return Err(SceneError { line, col, msg: format!("{} before any newmtl", key) });
+            },
+            None => continue,
+        };
+        let color = |args: &[(usize, &str)], what: &str| -> Result<Color, SceneError> {
+            if matches!(args.first().map(|a| a.1), Some("spectral") | Some("xyz")) {
+                return Err(SceneError { line, col: args[0].0, msg: format!("only rgb {} is supported", what) });
+            }
+            let c = numbers(line, args, 1, 3, what)?;
+            Ok(if c.len() == 3 { Color::new([c[0], c[1], c[2]]) } else { Color::new([c[0], c[0], c[0]]) })
+        };
+        match key {
+            "Kd" => desc.kd = color(args, key)?,
+            "Ks" => desc.ks = color(args, key)?,
+            "Ke" => desc.ke = color(args, key)?,
+            "map_Kd" => {
+                // the file name comes last, after any options (-s 1 1 1, -clamp on, ...)
+                let &(col, file) = args.last()
+                    .ok_or_else(|| SceneError { line, col, msg: "map_Kd needs a file name".to_string() })?;
+                let path = dir.join(file);
+                let tex = ImageTexture::load_png(&path.to_string_lossy())
+                    .map_err(|msg| SceneError { line, col, msg })?;
+                files.push(path);
+                desc.map_kd = Some(Arc::new(tex));
+            },
+            "Tf" => desc.tf = Some(color(args, key)?),
+            "Ns" => desc.ns = numbers(line, args, 1, 1, key)?[0],
+            "Ni" => desc.ni = numbers(line, args, 1, 1, key)?[0],
+            "d" => {
+                let args = if args.first().map(|a| a.1) == Some("-halo") { &args[1..] } else { args };
+                desc.d = numbers(line, args, 1, 1, key)?[0];
+            },
+            "Tr" => desc.d = 1.0 - numbers(line, args, 1, 1, key)?[0],
+            "illum" => {
+                let n = numbers(line, args, 1, 1, key)?[0];
+                if n < 0.0 || n.fract() != 0.0 {
+                    return Err(SceneError { line, col: args[0].0, msg: format!("bad illum model {}", n) });
+                }
+                desc.illum = n as u32;
+            },
+            _ => {}, // Ka, map_*, etc.
+        }
+    }
+    if let Some((name, desc)) = current {
+        materials.insert(name, desc.to_material());
+    }
+    Ok(())
+}
+
+//
+// obj
+//
+
+// faces (indices into the shared vertices) that use one material
+type Faces = (Arc<dyn Material>, Vec<[u32; 3]>);
+
+// a group's faces, sorted by material
+struct Group {
+    name: String,
+    meshes: Vec<Faces>,
+}
+
+impl Group {
+    fn new(name: String) -> Self {
+        Group { name, meshes: Vec::new() }
+    }
+
+    fn faces_for(&mut self, mat: &Arc<dyn Material>) -> &mut Vec<[u32; 3]> {
+        let i = match self.meshes.iter().position(|(m, _)| Arc::ptr_eq(m, mat)) {
+            Some(i) => i,
+            None => {
+                self.meshes.push((Arc::clone(mat), Vec::new()));
+                self.meshes.len() - 1
+            },
+        };
+        &mut self.meshes[i].1
+    }
+}
+
+fn parse_obj(src: &str, dir: &Path, name: &str, files: &mut Vec<PathBuf>) -> Result<Jumble, SceneError> {
+    let (mut positions, mut texcoords, mut normals) = (Vec::new(), Vec::new(), Vec::new());
+
+    // unique (v, vt, vn) combos become the vertices of the shared buffer
+    let mut verts = MeshVertices::default();
+    let mut vert_uvs: Vec<Vec2> = Vec::new();
+    let mut vert_normals: Vec<Vec3> = Vec::new();
+    let (mut any_uvs, mut any_normals) = (false, false);
+    let mut vert_index: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    let default_mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([0.8, 0.8, 0.8])));
+    let mut material = Arc::clone(&default_mat);
+
+    let mut groups = vec![Group::new(String::new())]; // the first is for faces outside of any group
+
+    for (l, text) in src.lines().enumerate() {
+        let line = l + 1;
+        let words = words(text);
+        let (col, key) = match words.first() {
+            Some(&w) => w,
+            None => continue,
+        };
+        let args = &words[1..];
+        match key {
+            "v" => {
+                let n = numbers(line, args, 3, 4, "v")?; // (w is only for rational curves)
+                positions.push(Vec3::new([n[0], n[1], n[2]]));
+            },
+            "vt" => {
+                let n = numbers(line, args, 1, 3, "vt")?;
+                texcoords.push(Vec2::new([n[0], if n.len() > 1 { n[1] } else { 0.0 }]));
+            },
+            "vn" => {
+                let n = numbers(line, args, 3, 3, "vn")?;
+                normals.push(Vec3::new([n[0], n[1], n[2]]));
+            },
+            "f" => {
+                if args.len() < 3 {
+                    return Err(SceneError { line, col, msg: format!("face needs at least 3 vertices, found {}", args.len()) });
+                }
+                let mut face = Vec::with_capacity(args.len());
+                for &(col, word) in args {
+                    let key = face_vertex(word, positions.len(), texcoords.len(), normals.len())
+                        .map_err(|msg| SceneError { line, col, msg })?;
+                    let idx = *vert_index.entry(key).or_insert_with(|| {
+                        verts.positions.push(positions[key.0]);
+                        vert_uvs.push(key.1.map_or(Vec2::zero(), |t| texcoords[t]));
+                        vert_normals.push(key.2.map_or(Vec3::zero(), |n| normals[n])); // zero: use the face normal
+                        (verts.positions.len() - 1) as u32
+                    });
+                    any_uvs |= key.1.is_some();
+                    any_normals |= key.2.is_some();
+                    face.push(idx);
+                }
+                // fan (fine for the convex polygons obj files are supposed to have)
+                let faces = groups.last_mut().unwrap().faces_for(&material);
+                for i in 1..face.len()-1 {
+                    faces.push([face[0], face[i], face[i+1]]);
+                }
+            },
+            "g" | "o" => {
+                let name = args.iter().map(|a| a.1).collect::<Vec<_>>().join(" ");
+                groups.push(Group::new(if name.is_empty() { "anon".to_string() } else { name }));
+            },
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(SceneError { line, col, msg: "mtllib needs a file name".to_string() });
+                }
+                for &(_, file) in args {
+                    load_mtl(&dir.join(file), &mut materials, files)?;
+                }
+            },
+            "usemtl" => {
+                let name = args.iter().map(|a| a.1).collect::<Vec<_>>().join(" ");
+                material = match materials.get(&name) {
+                    Some(mat) => Arc::clone(mat),
+                    None => return Err(SceneError { line, col: args.first().map_or(col, |a| a.0),
+                                                    msg: format!("undefined material '{}' (missing mtllib?)", name) }),
+                };
+            },
+            _ => {}, // s, l, p, vp, curves and friends
+        }
+    }
+
+    if any_uvs {
+        verts.uvs = Some(vert_uvs);
+    }
+    if any_normals {
+        verts.normals = Some(vert_normals);
+    }
+    let verts = Arc::new(verts);
+
+    let mut root = Jumble::new();
+    root.name = name.to_string();
+    for (i, group) in groups.into_iter().enumerate() {
+        let meshes: Vec<Arc<dyn Intersectable>> = group.meshes.into_iter()
+            .filter(|(_, faces)| !faces.is_empty())
+            .map(|(mat, faces)| Arc::new(TriangleMesh::new(Arc::clone(&verts), faces, mat)) as Arc<dyn Intersectable>)
+            .collect();
+        if i == 0 {
+            meshes.into_iter().for_each(|m| root.add(m));
+        } else if !meshes.is_empty() {
+            let mut jumble = Jumble::new();
+            jumble.name = group.name;
+            meshes.into_iter().for_each(|m| jumble.add(m));
+            root.add(Arc::new(jumble));
+        }
+    }
+    Ok(root)
+}
+
+// v, v/vt, v//vn or v/vt/vn (1-based, or negative to count back from the latest)
+fn face_vertex(word: &str, nv: usize, nt: usize, nn: usize) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let index = |s: &str, count: usize, what: &str| -> Result<usize, String> {
+        let i: i64 = s.parse().map_err(|_| format!("bad {} index '{}' in face vertex '{}'", what, s, word))?;
+        let resolved = if i > 0 { i - 1 } else { count as i64 + i };
+        if i == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(format!("{} index {} out of range (have {})", what, i, count));
+        }
+        Ok(resolved as usize)
+    };
+    let parts: Vec<&str> = word.split('/').collect();
+    if parts.len() > 3 {
+        return Err(format!("bad face vertex '{}'", word));
+    }
+    let v = index(parts[0], nv, "position")?;
+    let t = match parts.get(1) {
+        Some(s) if !s.is_empty() => Some(index(s, nt, "texcoord")?),
+        _ => None,
+    };
+    let n = match parts.get(2) {
+        Some(s) if !s.is_empty() => Some(index(s, nn, "normal")?),
+        _ => None,
+    };
+    Ok((v, t, n))
+}
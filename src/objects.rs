@@ -12,7 +12,10 @@ use std::fmt;
 
 use crate::*;
 use crate::materials::*;
-pub use std::rc::Rc;  // FIXME: purportedly we don't have to `use` in every module, but not working
+use crate::bvh::Bvh;
+use crate::lights::{Light, transformed_radius};
+use std::sync::OnceLock;
+pub use std::sync::Arc;  // FIXME: purportedly we don't have to `use` in every module, but not working
 
 // hit record
 #[derive(Debug)]
@@ -21,7 +24,12 @@ pub struct HitRecord {
     pub normal: Vec3,
     pub t: f32,
     pub front_face: bool,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
+    pub uv: Vec2,   // texture coordinates
+    pub bary: Vec3, // barycentric coords when a triangle was hit (weights of its 1st, 2nd, 3rd vertex)
+    pub outer_eta: f32, // eta of what's on the other side of the surface from the material (see MediumStack)
+    pub object: u32,    // id of the innermost Jumble hit (see Jumble::id)
+    object_t: f32,      // (the t it was set for, so outer Jumbles know whether it's still theirs)
 }
 
 impl fmt::Display for HitRecord{
@@ -38,35 +46,60 @@ impl HitRecord {
             point: Vec3::zero(),
             normal: Vec3::zero(),
             front_face: true,
-            material: Rc::new(Lambertian::new(Color::white())),
+            material: Arc::clone(default_material()),
+            uv: Vec2::zero(),
+            bary: Vec3::zero(),
+            outer_eta: 1.0,
+            object: 0,
+            object_t: f32::NAN,
         }
     }
 }
 
+// what a fresh HitRecord points at (shared, since one's made for every ray)
+fn default_material() -> &'static Arc<dyn Material> {
+    static DEFAULT: OnceLock<Arc<dyn Material>> = OnceLock::new();
+    DEFAULT.get_or_init(|| Arc::new(Lambertian::new(Color::white())))
+}
+
+impl Default for HitRecord {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // result of Ray intersection with some Intersectable
 pub enum Shot {
     Hit,
     Miss,
 }
 
-pub trait Intersectable {
+// Send + Sync so the scene can be shared by the render threads
+pub trait Intersectable: Send + Sync {
     // intersect ray with this object or collection
     // - Range is global allowed distance along ray
     // - HitRecord is updated when there is an intersection
     // - indent is used to print debugging output
     fn intersect(&self, ray: Ray, rng: &Range, hit: &mut HitRecord, indent_by: usize) -> Shot;
+
+    // axis-aligned bounds in the space of whoever holds this
+    // (AABoundingBox::infinite() if it has no idea)
+    fn bbox(&self) -> AABoundingBox;
+
+    // add anything that glows to lights, in world space (to_world takes this object's space there)
+    fn gather_lights(&self, to_world: &Matrix, lights: &mut Vec<Light>) {}
 }
 
 // buncha stuff that can be intersected, including itself
 //#[derive(Debug)]
 pub struct Jumble {
     pub name: String,
-    arr: Vec<Rc<dyn Intersectable>>,
+    arr: Vec<Arc<dyn Intersectable>>,
     csys: CoordSys,
     // csys: Matrix,
     // csys_inv: Matrix,
     // csys_inv_xpose: Matrix,
-    //bbox: AABoundingBox, //TODO
+    bvh: OnceLock<Bvh>, // built the first time it's needed, thrown away when objects are added
 }
 
 impl Jumble {
@@ -75,6 +108,7 @@ impl Jumble {
             name: String::from("anon"),
             arr: Vec::new(),
             csys: CoordSys::identity(),
+            bvh: OnceLock::new(),
             // Matrix::identity(),
             // csys_inv: Matrix::identity().inverse(),
             // csys_inv_xpose: Matrix::identity().inverse().transpose(),
@@ -85,15 +119,37 @@ impl Jumble {
         self.csys
     }
 
-    pub fn add(&mut self, obj: Rc<dyn Intersectable>) {
-        self.arr.push(obj)
+    // which Jumble got hit, for the object id AOV (same name, same id)
+    pub fn id(&self) -> u32 {
+        fnv1a(self.name.bytes()) as u32
+    }
+
+    pub fn add(&mut self, obj: Arc<dyn Intersectable>) {
+        self.arr.push(obj);
+        self.bvh.take();
     }
 
     pub fn set_csys(&mut self, csys: CoordSys) {
         self.csys = csys;
     }
 
-    //fn update_bbox() {... // TODO
+    pub fn len(&self) -> usize {
+        self.arr.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.arr.is_empty()
+    }
+
+    pub fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| Bvh::build(&self.arr))
+    }
+}
+
+impl Default for Jumble {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Intersectable for Jumble {
@@ -101,7 +157,7 @@ impl Intersectable for Jumble {
         // ugh: this is two big lines just to indent by a few spaces; TODO: macro me?
         let indent = vec![' '; indent_by];
         let indent: String = indent.iter().cloned().collect();
-        if crate::DEBUG {
+        if debug() {
             println!("{}intersect {{{}}} with {}", indent, self.name, ray);
 
             // println!("{}csys: ",indent);
@@ -114,47 +170,48 @@ impl Intersectable for Jumble {
 
         // transform ray into this Jumble's coordinate system
         let ray = self.csys.ray_in(ray);
-        if crate::DEBUG {
+        if debug() {
             println!("{} - transformed {}", indent, ray);
         }
 
-        let mut hit_something = false;
-        for obj in self.arr.iter() {  // NOTE: we'll leave parallelization for another day
-            if crate::DEBUG {
-                //print_type_of(obj); // prints interfaces obj implements (i.e., not useful)
-                //println!("obj: {:?}", obj); // can just be too much (e.g., array of objects)
-                // println!("{}rng: {:?}", indent, rng);
-            }
-            match obj.intersect(ray, rng, hit, indent_by+2) {
-                Shot::Hit => { // NOTE: a long-winded way to say `hit_something |= intersect()
-                    hit_something = true;
-                    // if crate::DEBUG {
-                    //     println!("{} - hit", indent);
-                    // }
-                },
-                Shot::Miss => {
-                    // if crate::DEBUG {
-                    //     println!("{} - miss",indent);
-                    // }
-                },
-            }
-        }
+        // the bvh only bothers the children whose bounds the ray passes through
+        let hit_something = match self.bvh().intersect(&self.arr, ray, rng, hit, indent_by+2) {
+            Shot::Hit => true,
+            Shot::Miss => false,
+        };
         if hit_something {
             // transform hit point and its normal out of csys
-            if crate::DEBUG {
+            if debug() {
                 println!("{} - pre-xform: {}", indent, hit);
             }
             hit.point = self.csys.point_out(hit.point);
             hit.normal = self.csys.normal_out(hit.normal);
+            // one of ours, unless a Jumble inside already claimed this very hit
+            if hit.object_t != hit.t {
+                hit.object = self.id();
+                hit.object_t = hit.t;
+            }
 
-            if crate::DEBUG {
+            if debug() {
                  println!("{} - pst-xform: {}", indent, hit);
             }
             return Shot::Hit;
         }
-        //if crate::DEBUG { println!("{}air rayyyyy!", indent);}
+        //if debug() { println!("{}air rayyyyy!", indent);}
         Shot::Miss
     }
+
+    fn bbox(&self) -> AABoundingBox {
+        // m_out is what places the children in the world
+        self.bvh().bbox().transform(&self.csys.m_out)
+    }
+
+    fn gather_lights(&self, to_world: &Matrix, lights: &mut Vec<Light>) {
+        let to_world = *to_world * self.csys.m_out;
+        for obj in &self.arr {
+            obj.gather_lights(&to_world, lights);
+        }
+    }
 }
 
 
@@ -162,7 +219,7 @@ impl Intersectable for Jumble {
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f32,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
 }
 
 impl fmt::Display for Sphere {
@@ -175,7 +232,7 @@ impl Intersectable for Sphere {
     fn intersect(&self, ray: Ray, rng: &Range, hit: &mut HitRecord, indent_by: usize) -> Shot {
         let indent = vec![' '; indent_by];
         let indent: String = indent.iter().cloned().collect();
-        if crate::DEBUG {
+        if debug() {
             println!("{}{}", indent, self);
         }
         let oc = ray.origin - self.center;
@@ -183,12 +240,12 @@ impl Intersectable for Sphere {
         let half_b = oc.dot(ray.dir);
         let c = oc.len_squared() - self.radius*self.radius;
         let discriminant = half_b*half_b - a*c;
-        if DEBUG {
+        if debug() {
             println!("{}rng: {:?}", indent, rng);
             println!("{}hit.t: {}", indent, hit.t);
         }
         if discriminant < 0.0 {
-            if crate::DEBUG {
+            if debug() {
                 println!("{} - miss", indent);
             }
             return Shot::Miss;
@@ -202,12 +259,12 @@ impl Intersectable for Sphere {
         //  - if at least one > rng.min: use the larger
         //  - check selected is inside range
         let t = if t0 > rng.min && t1 > rng.min { t0.min(t1) } else { t0.max(t1) };
-        if DEBUG {
+        if debug() {
             println!("{}t: {}", indent, t); // what if t is behind ray origin??
             println!("{}t0: {}, t1: {}", indent, t0, t1);
         }
-        if t.outside(&rng) || t > hit.t {
-            if crate::DEBUG {
+        if t.outside(rng) || t > hit.t {
+            if debug() {
                 println!("{} - miss", indent);
             }
             return Shot::Miss;
@@ -218,13 +275,15 @@ impl Intersectable for Sphere {
         // set normal to oppose ray direction and indicate whether it's a
         // hit against front face or back face of geometry
         let normal = (hit.point - self.center) / self.radius; // ** negative radius inverts normal trick **
-        hit.front_face = if dot(normal, ray.dir) < 0.0 {true} else {false};
+        hit.front_face = dot(normal, ray.dir) < 0.0;
 
         hit.t = t;
         hit.normal = if hit.front_face {normal} else {-normal};
-        hit.material = Rc::clone(&self.material);
+        hit.material = Arc::clone(&self.material);
+        hit.uv = Sphere::uv(normal * self.radius.signum());
+        hit.bary = Vec3::zero();
 
-        if crate::DEBUG {
+        if debug() {
             // println!("oc: {}",oc);
             // println!("a: {}",a);
             // println!("half_b: {}",half_b);
@@ -235,14 +294,42 @@ impl Intersectable for Sphere {
 
         Shot::Hit
     }
+
+    fn bbox(&self) -> AABoundingBox {
+        let r = Vec3::new([self.radius.abs(); 3]); // ** negative radius trick strikes again **
+        AABoundingBox::new(self.center - r, self.center + r)
+    }
+
+    fn gather_lights(&self, to_world: &Matrix, lights: &mut Vec<Light>) {
+        if !self.material.emits() {
+            return;
+        }
+        match transformed_radius(to_world, self.radius) {
+            // (to_world only scales and turns it, so its transpose turns back, scaled)
+            Some(radius) => lights.push(Light::sphere(to_world.apply_to_point(self.center), radius,
+                                                      to_world.transpose(), Arc::clone(&self.material))),
+            // still lights things up when bounced into, just noisily
+            None => if debug() {
+                println!("NOTE: squished light {} can't be sampled directly", self);
+            },
+        }
+    }
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f32, mat: Rc<dyn Material>) -> Sphere {
+    pub fn new(center: Vec3, radius: f32, mat: Arc<dyn Material>) -> Sphere {
         Sphere {
             center,
             radius,
             material: mat,
         }
     }
+
+    // texture coords of a point on the unit sphere: u goes around the y axis starting at
+    // -x, v from the bottom (-y) to the top
+    pub fn uv(p: Vec3) -> Vec2 {
+        let theta = (-p.y()).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+        Vec2::new([phi / (2.0*PI), theta / PI])
+    }
 }
@@ -0,0 +1,214 @@
+//
+// the principled ("uber") material
+//
+// One material with the knobs artists know from Disney's principled BRDF (Burley,
+// "Physically Based Shading at Disney", 2012): base color, metallic, roughness,
+// specular, transmission, clearcoat and sheen. Rather than a BRDF of its own it's a
+// stack of the old lobes -- a Shiny clearcoat over a Shiny specular layer over a
+// Lambertian (plus sheen) or Transparent base -- and each hit picks one of them at
+// random, in proportion to how much light it'd send back.
+//
+
+use crate::*;
+use crate::materials::{LightScatter, Medium};
+use crate::settings::RenderSettings;
+use crate::textures::{Texture, SolidColor};
+use LightScatter::{ Attenuated, Specular, Absorbed };
+use rand::{Rng, RngCore};
+use std::sync::OnceLock;
+
+// all the knobs are in [0,1] (anything outside is clamped when used), except eta
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: f32,            // 0 dielectric, 1 metal (tinted reflections, no diffuse)
+    pub roughness: f32,           // of the specular and transmission lobes
+    pub specular: f32,            // strength of a dielectric's reflections (0.5 is ~4%, like plastic)
+    pub transmission: f32,        // glassiness of the dielectric part
+    pub clearcoat: f32,           // a clear varnish on top (car paint)
+    pub clearcoat_roughness: f32,
+    pub sheen: f32,               // a soft rim of light at grazing angles (cloth)
+    pub sheen_tint: f32,          // 0 white sheen, 1 tinted by the base color
+    pub eta: f32,                 // of what's inside, when it transmits
+}
+
+// what a hit's light is split into (each lobe's own attenuation multiplies these)
+struct LobeWeights {
+    clearcoat: Color,
+    specular: Color,
+    transmission: f32,
+    diffuse: f32,
+}
+
+impl LobeWeights {
+    // chances of picking each lobe, in the order above
+    fn probabilities(&self) -> [f32; 4] {
+        let mean = |c: Color| (c[0] + c[1] + c[2]) / 3.0;
+        let w = [mean(self.clearcoat), mean(self.specular), self.transmission, self.diffuse];
+        let total: f32 = w.iter().sum();
+        if total > 0.0 { w.map(|x| x / total) } else { [0.0; 4] }
+    }
+}
+
+fn white() -> Arc<dyn Texture> {
+    static WHITE: OnceLock<Arc<dyn Texture>> = OnceLock::new();
+    Arc::clone(WHITE.get_or_init(|| Arc::new(SolidColor::new(Color::new([1.0, 1.0, 1.0])))))
+}
+
+fn schlick(f0: f32, cos: f32) -> f32 {
+    f0 + (1.0 - f0) * (1.0 - cos).powi(5)
+}
+
+impl Principled {
+    // a plain, slightly plasticky dielectric; set the other knobs directly
+    pub fn new(base_color: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(base_color)))
+    }
+
+    pub fn textured(base_color: Arc<dyn Texture>) -> Self {
+        Self { base_color,
+               metallic: 0.0,
+               roughness: 0.5,
+               specular: 0.5,
+               transmission: 0.0,
+               clearcoat: 0.0,
+               clearcoat_roughness: 0.03,
+               sheen: 0.0,
+               sheen_tint: 0.5,
+               eta: 1.5,
+        }
+    }
+
+    // the lobes, made of the old materials
+    fn diffuse_lobe(&self) -> Lambertian {
+        Lambertian::textured(Arc::clone(&self.base_color))
+    }
+
+    fn specular_lobe(&self) -> Shiny {
+        Shiny::textured(white(), self.roughness.clamp(0.0, 1.0))
+    }
+
+    fn transmission_lobe(&self) -> Transparent {
+        Transparent::textured(Arc::clone(&self.base_color), self.roughness.clamp(0.0, 1.0), self.eta)
+    }
+
+    fn clearcoat_lobe(&self) -> Shiny {
+        Shiny::textured(white(), self.clearcoat_roughness.clamp(0.0, 1.0))
+    }
+
+    // how the light at hit splits between the lobes: the clearcoat takes its (Fresnel)
+    // share off the top, the specular layer reflects its share of what's left, and the
+    // rest goes into the base (through it if transmissive, into the diffuse if not)
+    fn weights(&self, ray: Ray, hit: &HitRecord) -> LobeWeights {
+        let metallic = self.metallic.clamp(0.0, 1.0);
+        let transmission = self.transmission.clamp(0.0, 1.0);
+        if !hit.front_face && transmission > 0.0 {
+            // on the way out of something glassy: only the glass is in here
+            return LobeWeights { clearcoat: Color::black(), specular: Color::black(),
+                                 transmission: 1.0, diffuse: 0.0 };
+        }
+        let cos = (-1.0*hit.normal.dot(ray.dir.normalize())).clamp(0.0, 1.0);
+        let base = self.base_color.value(hit.uv, hit.point);
+
+        let coat = self.clearcoat.clamp(0.0, 1.0) * schlick(0.04, cos);
+        let dielectric = schlick(0.08 * self.specular.clamp(0.0, 1.0), cos);
+        let metal = Color::new([schlick(base[0], cos), schlick(base[1], cos), schlick(base[2], cos)]);
+        let under = (1.0 - coat) * (1.0 - metallic);
+
+        LobeWeights {
+            clearcoat: coat * Color::new([1.0, 1.0, 1.0]),
+            specular: (1.0 - coat) * (metallic * metal + ((1.0 - metallic) * (1.0 - transmission) * dielectric) * Color::new([1.0, 1.0, 1.0])),
+            transmission: under * transmission, // (Transparent does its own Fresnel)
+            diffuse: under * (1.0 - transmission) * (1.0 - dielectric),
+        }
+    }
+
+    // extra diffuse "albedo" toward dir from sheen, strongest where dir and the ray are far apart
+    fn sheen(&self, ray: Ray, hit: &HitRecord, dir: Vec3) -> Color {
+        let sheen = self.sheen.clamp(0.0, 1.0);
+        if sheen <= 0.0 {
+            return Color::black();
+        }
+        let base = self.base_color.value(hit.uv, hit.point);
+        let lum = 0.2126*base[0] + 0.7152*base[1] + 0.0722*base[2];
+        let tint = if lum > 0.0 { base / lum } else { Color::new([1.0, 1.0, 1.0]) };
+        let t = self.sheen_tint.clamp(0.0, 1.0);
+        let color = (1.0 - t) * Color::new([1.0, 1.0, 1.0]) + t * tint;
+
+        let half = (dir.normalize() - ray.dir.normalize()).normalize();
+        let cos_d = half.dot(dir.normalize()).abs().min(1.0);
+        (sheen * (1.0 - cos_d).powi(5)) * color
+    }
+}
+
+impl Material for Principled {
+    fn log(&self) -> String {
+        format!("⊕ Principled c: {:?} metallic:{} roughness:{} specular:{} transmission:{} clearcoat:{} ({}) sheen:{} ({}) η:{}",
+                self.base_color, self.metallic, self.roughness, self.specular, self.transmission,
+                self.clearcoat, self.clearcoat_roughness, self.sheen, self.sheen_tint, self.eta)
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.base_color.value(hit.uv, hit.point)
+    }
+
+    fn medium(&self) -> Option<Medium> {
+        if self.transmission > 0.0 {
+            Some(Medium { eta: self.eta, absorption: Color::black() })
+        } else {
+            None
+        }
+    }
+
+    // pick a lobe and let it scatter (weighted by 1/the chance of picking it)
+    fn scatter(&self, ray: Ray, hit: &HitRecord, settings: &RenderSettings, rng: &mut dyn RngCore, indent_by: usize) -> LightScatter {
+        let indent = vec![' '; indent_by];
+        let indent: String = indent.iter().cloned().collect();
+        let weights = self.weights(ray, hit);
+        let p = weights.probabilities();
+
+        let mut u: f32 = rng.gen();
+        let lobe = p.iter().position(|&pi| { u -= pi; u < 0.0 && pi > 0.0 })
+            .or_else(|| p.iter().rposition(|&pi| pi > 0.0)); // (rounding)
+        if settings.debug {
+            println!("{} ⊕ Principled.scatter: lobe chances (coat, spec, trans, diff): {:?} picked: {:?}", indent, p, lobe);
+        }
+
+        // the mirror-ish lobes can't be light sampled, so they say so
+        let mirror = |scattered: LightScatter, weight: Color, pi: f32| match scattered {
+            Attenuated(c, r) | Specular(c, r) => Specular(weight * c / pi, r),
+            Absorbed => Absorbed,
+        };
+        match lobe {
+            Some(0) => mirror(self.clearcoat_lobe().scatter(ray, hit, settings, rng, indent_by), weights.clearcoat, p[0]),
+            Some(1) => mirror(self.specular_lobe().scatter(ray, hit, settings, rng, indent_by), weights.specular, p[1]),
+            Some(2) => mirror(self.transmission_lobe().scatter(ray, hit, settings, rng, indent_by),
+                              weights.transmission * Color::new([1.0, 1.0, 1.0]), p[2]),
+            Some(3) => match self.diffuse_lobe().scatter(ray, hit, settings, rng, indent_by) {
+                // (the diffuse lobe is the only one bsdf and pdf know about, so this is bsdf/pdf)
+                Attenuated(c, r) | Specular(c, r) =>
+                    Attenuated((weights.diffuse / p[3]) * (c + self.sheen(ray, hit, r.dir)), r),
+                Absorbed => Absorbed,
+            },
+            _ => Absorbed, // black all over
+        }
+    }
+
+    // light sampling only sees the diffuse (and sheen) lobe, the others are mirror-like
+    fn bsdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> Color {
+        let weights = self.weights(ray, hit);
+        if weights.diffuse <= 0.0 {
+            return Color::black();
+        }
+        let diffuse = self.diffuse_lobe();
+        weights.diffuse * (diffuse.bsdf(ray, hit, dir, settings)
+                           + diffuse.pdf(ray, hit, dir, settings) * self.sheen(ray, hit, dir))
+    }
+
+    fn pdf(&self, ray: Ray, hit: &HitRecord, dir: Vec3, settings: &RenderSettings) -> f32 {
+        let p = self.weights(ray, hit).probabilities();
+        if p[3] <= 0.0 {
+            return 0.0;
+        }
+        p[3] * self.diffuse_lobe().pdf(ray, hit, dir, settings)
+    }
+}
@@ -0,0 +1,522 @@
+//
+// render module
+//
+// shoots the rays, splitting the image into tiles for a pool of threads
+//
+
+use crate::*;
+use crate::settings::{RenderSettings, Snapshot};
+use crate::lights::Lights;
+use crate::materials::MediumStack;
+use crate::film::{Film, PixelVariance};
+use crate::checkpoint::{self, Checkpoint};
+use crate::aov::Aov;
+use materials::LightScatter::{ Attenuated, Specular, Absorbed };
+use rand::{RngCore, SeedableRng};
+use rand::rngs::StdRng;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
+
+// color of ray(origin, dir)
+pub fn ray_color(ray: Ray, scene: &Jumble, lights: &Lights, settings: &RenderSettings, depth: i32,
+                 rng: &mut dyn RngCore, indent_by: usize) -> Color {
+    trace(ray, scene, lights, &MediumStack::new(), settings, depth, None, None, rng, indent_by).opaque()
+}
+
+// color of ray(origin, dir), and what it hit first (None for a miss), for the AOVs
+pub fn ray_color_and_hit(ray: Ray, scene: &Jumble, lights: &Lights, settings: &RenderSettings, depth: i32,
+                         rng: &mut dyn RngCore, indent_by: usize) -> (Color, Option<HitRecord>) {
+    let mut first_hit = None;
+    let color = trace(ray, scene, lights, &MediumStack::new(), settings, depth, None, Some(&mut first_hit), rng, indent_by);
+    (color.opaque(), first_hit)
+}
+
+// Light gets here two ways: by bouncing into a light (whatever scatter picked) and by
+// aiming straight at one from each bounce. Both see the same lights, so each is weighted
+// by how good it is at finding the light in question (multiple importance sampling,
+// Veach's power heuristic). `bsdf_pdf` is the density with which the previous bounce
+// picked this ray, None when it came from the camera or a mirror-like material (which
+// never aim at lights, so what they bounce into counts in full). `media` are what the
+// ray is travelling through, which soak up some light along the way. The hit, if any, ends
+// up in first_hit when there's one to put it in.
+fn trace(ray: Ray, scene: &Jumble, lights: &Lights, media: &MediumStack, settings: &RenderSettings, depth: i32,
+         bsdf_pdf: Option<f32>, first_hit: Option<&mut Option<HitRecord>>, rng: &mut dyn RngCore, indent_by: usize) -> Color {
+    let indent = vec![' '; indent_by];
+    let indent: String = indent.iter().cloned().collect();
+
+    if depth <= 0 { return Color::black(); } // you can only dive so deep...
+    if settings.debug { println!("{}{}: starting ray_color...", indent, settings.max_depth-depth); }
+
+    let mut hit = HitRecord::new();
+    match scene.intersect(ray, &Range::default(), &mut hit, indent_by) {
+        Shot::Hit => {
+            // (for normals as colors and such, see aov.rs)
+            if settings.debug {
+                println!("{}{}: hit! {}", indent, settings.max_depth-depth, hit);
+            }
+            // (t is the same in every Jumble's coordinates, so this is the distance in world space)
+            let transmittance = media.current().transmittance(hit.t * ray.dir.len());
+            hit.outer_eta = media.outside_of(&hit.material, hit.front_face).eta;
+
+            let mut color = hit.material.emitted(ray, &hit);
+            if let Some(pdf) = bsdf_pdf {
+                if !color.is_black() {
+                    color *= power_heuristic(pdf, lights.pdf(ray.origin, ray.dir));
+                }
+            }
+
+            // light it directly (the last bounce doesn't, since its scatter wouldn't go anywhere to balance it)
+            if depth > 1 {
+                color += direct_light(ray, &hit, scene, lights, media, settings, rng, indent_by);
+            }
+
+            let scattered = match hit.material.scatter(ray, &hit, settings, rng, indent_by) {
+                Attenuated(attenuation, scattered) => {
+                    let pdf = hit.material.pdf(ray, &hit, scattered.dir.normalize(), settings);
+                    Some((attenuation, scattered, pdf))
+                },
+                Specular(attenuation, scattered) => Some((attenuation, scattered, 0.0)),
+                Absorbed => None,
+            };
+            if let Some((attenuation, scattered, pdf)) = scattered {
+                let bsdf_pdf = if pdf > 0.0 && !lights.is_empty() { Some(pdf) } else { None };
+
+                // went through the surface, into (or out of) what's inside it
+                let crossed;
+                let media = match hit.material.medium() {
+                    Some(medium) if scattered.dir.dot(hit.normal) < 0.0 => {
+                        crossed = media.crossed(&hit.material, medium, hit.front_face);
+                        &crossed
+                    },
+                    _ => media,
+                };
+                color += attenuation*trace(scattered, scene, lights, media, settings, depth-1, bsdf_pdf, None, rng, indent_by);
+            }
+            if let Some(first_hit) = first_hit {
+                *first_hit = Some(hit);
+            }
+            transmittance * color
+        },
+        Shot::Miss => {
+            if settings.debug {
+                println!("{}{}: miss.", indent, settings.max_depth-depth);
+            }
+            media.current().transmittance(f32::INFINITY) * settings.background.color(ray.dir)
+        }
+    }
+}
+
+// light arriving at hit straight from a random light (if nothing's in the way)
+fn direct_light(ray: Ray, hit: &HitRecord, scene: &Jumble, lights: &Lights, media: &MediumStack,
+                settings: &RenderSettings, rng: &mut dyn RngCore, indent_by: usize) -> Color {
+    let sample = match lights.sample(hit.point, rng) {
+        Some(sample) if !sample.emitted.is_black() => sample,
+        _ => return Color::black(),
+    };
+    let bsdf_pdf = hit.material.pdf(ray, hit, sample.dir, settings);
+    if bsdf_pdf <= 0.0 {
+        return Color::black(); // can't scatter that way (or won't say)
+    }
+    let bsdf = hit.material.bsdf(ray, hit, sample.dir, settings);
+    if bsdf.is_black() {
+        return Color::black();
+    }
+
+    // shadow ray, stopping just short of the light
+    let shadow = Ray::new(hit.point, sample.dir);
+    let mut blocker = HitRecord::new();
+    let rng = Range::new(Range::default().min, sample.dist * (1.0 - 1e-4));
+    if let Shot::Hit = scene.intersect(shadow, &rng, &mut blocker, indent_by) {
+        if settings.debug {
+            println!("{}in the shadow of {}", vec![' '; indent_by].iter().collect::<String>(), blocker);
+        }
+        return Color::black();
+    }
+    // (through the surface, the shadow ray's in whatever's on the other side)
+    let medium = match hit.material.medium() {
+        Some(medium) if sample.dir.dot(hit.normal) < 0.0 => media.crossed(&hit.material, medium, hit.front_face).current(),
+        _ => media.current(),
+    };
+    let transmittance = medium.transmittance(sample.dist);
+    power_heuristic(sample.pdf, bsdf_pdf) / sample.pdf * (bsdf * sample.emitted * transmittance)
+}
+
+// weight for a sample picked with density pdf when other could've picked it too
+fn power_heuristic(pdf: f32, other: f32) -> f32 {
+    let (a, b) = (pdf*pdf, other*other);
+    if a + b > 0.0 { a / (a + b) } else { 0.0 }
+}
+
+pub fn get_pixels_to_trace(settings: &RenderSettings) -> Vec<[u32; 2]> {
+    // indices of pixels to trace
+    let mut pixels: Vec<[u32; 2]> = Vec::new();
+    let (width, height) = (settings.image_width, settings.image_height);
+
+    // handy for debugging just a couple of intersections
+    //let start_row = if settings.debug {70} else {0};
+    let start_row = if settings.debug {height/2 +1} else {0};
+    let end_row = height;
+    //let end_row = height/2+1;
+    let step_y: usize = if settings.debug { height.try_into().unwrap() } else { 1 };
+
+    let start_col = if settings.debug {width/2 +1} else {0};
+    //let start_col = if settings.debug {125} else {0};
+    let end_col = width;
+    let step_x: usize = if settings.debug { (width+10).try_into().unwrap() } else { 1 };
+
+    for j in (start_row..end_row).step_by(step_y) {
+        for i in (start_col..end_col).step_by(step_x) {
+            if settings.debug { println!("i,j: {},{}", i,j); }
+            pixels.push([i, j]);
+        }
+    }
+    pixels
+}
+
+// a rendered image, ready for io::write_image
+#[derive(Debug)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<f32>,               // rgba, top row first
+    pub color_range: (Color, Color),  // min and max of the traced pixels
+    pub spp: Vec<u32>,                // samples each pixel got, top row first
+    pub aovs: Vec<(Aov, Vec<f32>)>,   // rgba like data (see aov.rs)
+}
+
+impl Image {
+    // spp as colors, from black (no samples) through blue, red and yellow to white (max),
+    // to see where adaptive sampling spent its time
+    pub fn heatmap(&self, max: u32) -> Vec<f32> {
+        let ramp = [[0.0, 0.0, 0.0], [0.05, 0.0, 0.5], [0.8, 0.05, 0.05], [1.0, 0.8, 0.0], [1.0, 1.0, 1.0]];
+        let mut data = Vec::with_capacity(self.spp.len() * 4);
+        for &n in self.spp.iter() {
+            let t = (n as f32 / max.max(1) as f32).clamp(0.0, 1.0) * (ramp.len() - 1) as f32;
+            let i = (t as usize).min(ramp.len() - 2);
+            let f = t - i as f32;
+            let (lo, hi) = (ramp[i], ramp[i + 1]);
+            data.extend_from_slice(&[lo[0] + (hi[0] - lo[0])*f, lo[1] + (hi[1] - lo[1])*f, lo[2] + (hi[2] - lo[2])*f, 1.0]);
+        }
+        data
+    }
+}
+
+// trace the scene through the camera, the main entry point
+//
+// Progressively (with a snapshot interval, a time limit or checkpoints), this goes over the
+// whole frame in passes that each double the samples so far (1, 2, 4, ... spp), saving what
+// it has to settings.output every so often and a checkpoint after every pass, and stopping
+// early once time's up. Sampling adaptively, the passes after that give every pixel that
+// hasn't settled down yet another spp. (Quietly; see render_with to hear how it's going.)
+pub fn render(scene: &Jumble, camera: &Camera, settings: &RenderSettings) -> Image {
+    render_with(Checkpoint::new(settings), scene, camera, settings, &mut |_| {})
+}
+
+// carry on a progressive render from a checkpoint (see checkpoint.rs)
+pub fn resume(state: Checkpoint, scene: &Jumble, camera: &Camera, settings: &RenderSettings) -> Image {
+    render_with(state, scene, camera, settings, &mut |_| {})
+}
+
+// how a render's getting on, for render_with's caller to show (or not); each prints as a
+// line or two of log
+#[derive(Debug, Clone)]
+pub enum Progress<'a> {
+    Started { tiles: usize, threads: usize, lights: usize },
+    Pass { pass: u32, spp: u32, seconds: f32 },
+    OutOfTime { pass: u32, spp: u32, behind: u32 }, // partway through pass, some pixels still at behind spp
+    Checkpoint { path: &'a str, result: Result<(), String> },
+    Snapshot { path: &'a str, result: Result<(), String> },
+    Adaptive { average_spp: f64 },                 // sampling adaptively, once it's done
+}
+
+impl fmt::Display for Progress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Progress::Started { tiles, threads, lights } => {
+                write!(f, "rendering {} tiles on {} threads", tiles, threads)?;
+                if *lights > 0 {
+                    write!(f, "\nsampling {} lights directly", lights)?;
+                }
+                Ok(())
+            },
+            Progress::Pass { pass, spp, seconds } => write!(f, "pass {}: {} spp ({:.1}s)", pass, spp, seconds),
+            Progress::OutOfTime { pass, spp, behind } =>
+                write!(f, "out of time partway through pass {}: some pixels got {} spp, the rest {}", pass, spp, behind),
+            Progress::Checkpoint { path, result: Ok(()) } => write!(f, "saved a checkpoint to {}", path),
+            Progress::Checkpoint { result: Err(msg), .. } => write!(f, "{}", msg),
+            Progress::Snapshot { path, result: Ok(()) } => write!(f, "saved a snapshot to {}", path),
+            Progress::Snapshot { result: Err(msg), .. } => write!(f, "couldn't save a snapshot: {}", msg),
+            Progress::Adaptive { average_spp } =>
+                write!(f, "adaptive sampling: {:.1} samples per pixel on average", average_spp),
+        }
+    }
+}
+
+// render (or resume, from a checkpoint; Checkpoint::new to start afresh), telling progress
+// how it's going
+pub fn render_with(mut state: Checkpoint, scene: &Jumble, camera: &Camera, settings: &RenderSettings,
+                   progress: &mut dyn FnMut(Progress)) -> Image {
+    set_debug(settings.debug); // for the intersectables
+    let (width, height) = (settings.image_width, settings.image_height);
+
+    // trace the tiles, then filter what they saw into the image
+    let tiles = get_tiles(settings);
+    let lights = Lights::gather(scene);
+    progress(Progress::Started { tiles: tiles.len(), threads: settings.thread_count().min(tiles.len()), lights: lights.len() });
+    let fingerprint = settings.checkpoint_path().map(|_| checkpoint::fingerprint(settings, camera));
+
+    let start = Instant::now();
+    let deadline = settings.time_limit().map(|limit| start + limit);
+    let mut last_snapshot = start;
+    let total = settings.samples_per_pixel;
+    // the fewest samples any pixel that wants more has had (after a pass that ran out of time,
+    // some pixels are a pass behind the rest)
+    let fewest = |state: &Checkpoint| tiles.iter().flatten()
+        .filter(|px| wants_more(state, **px, settings)).map(|px| state.count(*px)).min();
+    while let Some(done) = fewest(&state) {
+        let target = if !settings.progressive() {
+            total // (adaptive or not, trace_pixel takes it from there)
+        } else if done < total {
+            done + done.max(1).min(total - done)
+        } else {
+            (done + total).min(settings.max_samples())
+        };
+        let rendered = render_tiles(&tiles, &state, target, scene, &lights, camera, settings, deadline);
+        let finished = rendered.iter().all(|tile| tile.is_some());
+        for (tile, (tile_film, tile_variance)) in tiles.iter().zip(rendered.iter())
+            .filter_map(|(tile, rendered)| rendered.as_ref().map(|rendered| (tile, rendered))) {
+            state.film.merge(tile_film);
+            for (px, variance) in tile.iter().zip(tile_variance.iter()) {
+                let i = (px[1] * width + px[0]) as usize;
+                state.counts[i] = variance.n;
+                state.variance[i] = *variance;
+            }
+        }
+        if !settings.progressive() {
+            break;
+        }
+        if finished {
+            state.pass += 1;
+            progress(Progress::Pass { pass: state.pass, spp: target, seconds: start.elapsed().as_secs_f32() });
+        } else {
+            progress(Progress::OutOfTime { pass: state.pass + 1, spp: target, behind: done });
+        }
+        if let (Some(path), Some(fingerprint)) = (settings.checkpoint_path(), fingerprint) {
+            // (an error's only news: maybe the next one works)
+            progress(Progress::Checkpoint { path, result: state.save(path, fingerprint) });
+        }
+        if !finished {
+            break;
+        }
+        let snapshot_due = match settings.snapshot {
+            Snapshot::Never => false,
+            Snapshot::Seconds(secs) => last_snapshot.elapsed().as_secs_f32() >= secs,
+            Snapshot::Passes(every) => state.pass.is_multiple_of(every),
+        };
+        if snapshot_due && tiles.iter().flatten().any(|px| wants_more(&state, *px, settings)) {
+            let img = develop(&state, &tiles, settings);
+            progress(Progress::Snapshot { path: &settings.output, result: io::write_image(&settings.output, &img, settings) });
+            last_snapshot = Instant::now();
+        }
+    }
+
+    let img = develop(&state, &tiles, settings);
+    if settings.adaptive() {
+        let total: u64 = img.spp.iter().map(|&n| n as u64).sum();
+        progress(Progress::Adaptive { average_spp: total as f64 / (width*height) as f64 });
+    }
+    img
+}
+
+// the image so far: each traced pixel's filtered color, and how many samples it got
+fn develop(state: &Checkpoint, tiles: &[Vec<[u32; 2]>], settings: &RenderSettings) -> Image {
+    let (width, height) = (settings.image_width, settings.image_height);
+
+    // add an outline for debugging
+    let outline = if settings.debug { 1 } else { 0 };
+
+    // allocate dst image (white, so the outline shows when debugging)
+    let mut img = Image {
+        width: width + outline*2,
+        height: height + outline*2,
+        data: vec![1.0; usize::try_from(4*(width+outline*2)*(height+outline*2)).unwrap()],
+        color_range: (Color::white(), Color::black()),
+        spp: vec![0; usize::try_from((width+outline*2)*(height+outline*2)).unwrap()],
+        aovs: Vec::new(),
+    };
+    let rendered_aovs = settings.rendered_aovs();
+    let mut aovs: Vec<Vec<f32>> = vec![vec![0.0; img.data.len()]; rendered_aovs.len()];
+
+    for tile in tiles.iter() {
+        for px in tile.iter() {
+            let color = state.film.pixel(px[0], px[1]);
+            // update color minmax
+            for c in 0..4 {
+                img.color_range.0[c] = img.color_range.0[c].min(color[c]);
+                img.color_range.1[c] = img.color_range.1[c].max(color[c]);
+            }
+
+            // set pixel
+            let idx = pixel_idx(px, img.width, img.height);
+            img.data[idx + 0] = color[0];
+            img.data[idx + 1] = color[1];
+            img.data[idx + 2] = color[2];
+            img.data[idx + 3] = color[3];
+            img.spp[idx / 4] = state.count(*px);
+            for (layer, aov) in aovs.iter_mut().enumerate() {
+                let value = state.film.layer_pixel(1 + layer, px[0], px[1]);
+                aov[idx..idx + 4].copy_from_slice(&[value[0], value[1], value[2], value[3]]);
+            }
+        }
+    }
+    img.aovs = rendered_aovs.into_iter().zip(aovs).collect();
+    img
+}
+
+// get pixel index from inner image xy (the image height and width include any outline)
+fn pixel_idx(px: &[u32; 2], width: u32, height: u32) -> usize {
+    // idx = 4 * (current height * image width + current width)
+    usize::try_from(4*((height-1 - px[1]) * width + px[0])).unwrap()
+}
+
+// group the pixels to trace into square tiles (in row-major tile order)
+pub fn get_tiles(settings: &RenderSettings) -> Vec<Vec<[u32; 2]>> {
+    let ts = settings.tile_size;
+    let tiles_x = settings.image_width.div_ceil(ts);
+    let mut tiles: BTreeMap<u32, Vec<[u32; 2]>> = BTreeMap::new();
+    for px in get_pixels_to_trace(settings) {
+        tiles.entry((px[1] / ts) * tiles_x + px[0] / ts).or_default().push(px);
+    }
+    tiles.into_values().collect()
+}
+
+// Every pixel gets its own random numbers, from the seed and where it is, so a render
+// comes out the same every time (whichever thread gets to which pixel first)
+pub fn pixel_rng(seed: u64, px: [u32; 2]) -> StdRng {
+    StdRng::seed_from_u64(mix(seed ^ mix(((px[1] as u64) << 32) | px[0] as u64)))
+}
+
+// splitmix64's finalizer (nearby inputs, very different outputs)
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+// whether px should get more samples than it has: its spp, then (sampling adaptively) more
+// until it settles down or has had max-spp
+fn wants_more(state: &Checkpoint, px: [u32; 2], settings: &RenderSettings) -> bool {
+    let count = state.count(px);
+    count < settings.samples_per_pixel
+        || (settings.adaptive() && count < settings.max_samples() && state.variance(px).relative_error() > settings.adaptive_error)
+}
+
+// shoot a pixel's samples first..first+n, adding them into film and stats (how they varied
+// so far), and say how many it took: n, unless sampling adaptively in one go (not in
+// passes), when it's another spp at a time until the pixel's settled down
+pub fn trace_pixel(px: [u32; 2], first: u32, n: u32, stats: &mut PixelVariance, scene: &Jumble, lights: &Lights,
+                   camera: &Camera, settings: &RenderSettings, film: &mut Film) -> u32 {
+    let rng = &mut pixel_rng(settings.seed, px);
+    let mut sampler = settings.sampler.make(settings.samples_per_pixel); // (past which a stratified sampler goes random)
+    sampler.start_pixel(px, rng);
+    if first > 0 {
+        // a later pass: the same sampler (so its samples pick up where the last pass left
+        // off), but fresh random numbers for everything else
+        *rng = pixel_rng(settings.seed ^ mix(first as u64), px);
+    }
+
+    let aovs = settings.rendered_aovs();
+    let max = if settings.adaptive() && !settings.progressive() { settings.max_samples() } else { first + n };
+    let mut nsamples = first;
+    while nsamples < max {
+        let n = n.min(max - nsamples);
+        let rays = camera.gen_rays(px, nsamples, n, sampler.as_mut(), rng);
+        for (pos, ray) in rays {
+            if settings.debug {
+                println!("[pixel] ({}, {}) at {:?}:", px[0], px[1], pos);
+                //println!("shooting {}",ray);
+            }
+            let (color, hit) = ray_color_and_hit(ray, scene, lights, settings, settings.max_depth, rng, 0/*indent*/);
+            if settings.debug {
+                println!("color: {}\n", color);
+            }
+            stats.add(color);
+            if aovs.is_empty() {
+                film.add_sample(pos, &[color]);
+            } else {
+                film.add_sample(pos, &aov_sample(color, ray, hit.as_ref(), &aovs));
+            }
+        }
+        nsamples += n;
+        if !settings.adaptive() || stats.relative_error() <= settings.adaptive_error {
+            break;
+        }
+    }
+    if settings.debug && settings.adaptive() {
+        println!("{} samples (relative error {})", nsamples, stats.relative_error());
+    }
+    nsamples - first
+}
+
+// a sample's color and its AOVs (from what the ray hit first, see aov.rs)
+fn aov_sample(color: Color, ray: Ray, first_hit: Option<&HitRecord>, aovs: &[Aov]) -> Vec<Color> {
+    let mut colors = vec![color];
+    for aov in aovs.iter() {
+        colors.push(match first_hit {
+            Some(hit) => aov.value(ray, hit),
+            None => Aov::miss(),
+        });
+    }
+    colors
+}
+
+// Trace every tile's pixels that want more (see wants_more) from the samples they've had (in
+// state) up to target, each tile into its own film (covering the tile and as far around it as
+// the filter reaches), returned with each pixel's stats (samples taken and how they varied,
+// all told) in the same order as the tiles no matter which thread finished first -- so
+// merging them in order always adds things up the same way. Tiles nobody got to before the
+// deadline are None.
+pub fn render_tiles(tiles: &[Vec<[u32; 2]>], state: &Checkpoint, target: u32, scene: &Jumble, lights: &Lights,
+                    camera: &Camera, settings: &RenderSettings, deadline: Option<Instant>) -> Vec<Option<(Film, Vec<PixelVariance>)>> {
+    let nthreads = settings.thread_count().clamp(1, tiles.len().max(1));
+    let next_tile = AtomicUsize::new(0);
+    let (width, height) = (settings.image_width, settings.image_height);
+
+    let finished: Vec<(usize, (Film, Vec<PixelVariance>))> = thread::scope(|s| {
+        let workers: Vec<_> = (0..nthreads).map(|_| {
+            s.spawn(|| {
+                let mut done = Vec::new();
+                loop {
+                    let i = next_tile.fetch_add(1, Ordering::Relaxed);
+                    if i >= tiles.len() || deadline.is_some_and(|d| Instant::now() >= d) {
+                        break;
+                    }
+                    let mut film = Film::around(width, height, settings.filter, 1 + settings.rendered_aovs().len(), &tiles[i]);
+                    let variance = tiles[i].iter()
+                        .map(|px| {
+                            let (first, mut stats) = (state.count(*px), state.variance(*px));
+                            if first < target && wants_more(state, *px, settings) {
+                                trace_pixel(*px, first, target - first, &mut stats, scene, lights, camera, settings, &mut film);
+                            }
+                            stats
+                        })
+                        .collect();
+                    done.push((i, (film, variance)));
+                }
+                done
+            })
+        }).collect();
+        workers.into_iter().flat_map(|w| w.join().unwrap()).collect()
+    });
+
+    // put them back in tile order
+    let mut films: Vec<Option<(Film, Vec<PixelVariance>)>> = vec![None; tiles.len()];
+    for (i, film) in finished {
+        films[i] = Some(film);
+    }
+    films
+}
@@ -0,0 +1,274 @@
+//
+// samplers: where in a pixel (and on the lens) each of its samples goes
+//
+// Independent random samples clump and leave gaps, so a pixel's average wanders for a
+// long time before settling. These spread each pixel's samples out more evenly:
+// a jittered grid, or a low-discrepancy sequence (Halton, Sobol) randomized per pixel so
+// neighbors don't share the same pattern. Samples come in 2d pairs, asked for in a fixed
+// order per camera ray (first the pixel offset, then the lens), and each pair is its own
+// dimension of the sequence. Dimensions past what a sampler knows (if any) fall back to random.
+//
+
+use crate::*;
+use rand::{Rng, RngCore};
+use rand::seq::SliceRandom;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplerKind {
+    Independent, // plain random
+    Stratified,  // one random point in each cell of a grid
+    Halton,      // radical inverses in bases 2, 3, 5, 7, randomly shifted per pixel
+    Sobol,       // Sobol points, Owen scrambled per pixel (and per dimension)
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!("unknown sampler: {} (try independent, stratified, halton or sobol)", s)),
+        }
+    }
+}
+
+impl SamplerKind {
+    // a sampler for pixels of spp samples each
+    pub fn make(&self, spp: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(spp)),
+            SamplerKind::Halton => Box::new(HaltonSampler::default()),
+            SamplerKind::Sobol => Box::new(SobolSampler::default()),
+        }
+    }
+}
+
+// (the rng is passed in rather than kept so samplers don't care whose it is)
+pub trait Sampler {
+    // get ready for a new pixel
+    fn start_pixel(&mut self, px: [u32; 2], rng: &mut dyn RngCore);
+    // get ready for the pixel's index'th sample (from 0)
+    fn start_sample(&mut self, index: u32);
+    // the next pair of numbers in [0,1) for the current sample
+    fn get_2d(&mut self, rng: &mut dyn RngCore) -> [f32; 2];
+}
+
+fn random_2d(rng: &mut dyn RngCore) -> [f32; 2] {
+    [rng.gen(), rng.gen()]
+}
+
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn start_pixel(&mut self, px: [u32; 2], rng: &mut dyn RngCore) {}
+    fn start_sample(&mut self, index: u32) {}
+
+    fn get_2d(&mut self, rng: &mut dyn RngCore) -> [f32; 2] {
+        random_2d(rng)
+    }
+}
+
+// A jittered grid of as many of the spp samples as make a full grid (cols x rows, which
+// always covers the whole square; a partly used one would leave a corner unsampled), the
+// rest plain random.
+// Each dimension visits the cells in its own shuffled order so, e.g., the lens sample
+// in the top left of the pixel doesn't always land in the top left of the lens.
+pub struct StratifiedSampler {
+    cols: u32,
+    rows: u32,
+    order: Vec<Vec<u32>>, // cell for each sample, per dimension
+    index: u32,
+    dim: usize,
+}
+
+impl StratifiedSampler {
+    const DIMENSIONS: usize = 2;
+
+    pub fn new(spp: u32) -> Self {
+        let spp = spp.max(1);
+        let rows = (spp as f32).sqrt() as u32;
+        let cols = spp / rows;
+        Self { cols, rows, order: vec![Vec::new(); Self::DIMENSIONS], index: 0, dim: 0 }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel(&mut self, px: [u32; 2], rng: &mut dyn RngCore) {
+        for order in self.order.iter_mut() {
+            *order = (0..self.cols * self.rows).collect();
+            order.shuffle(rng);
+        }
+    }
+
+    fn start_sample(&mut self, index: u32) {
+        self.index = index;
+        self.dim = 0;
+    }
+
+    fn get_2d(&mut self, rng: &mut dyn RngCore) -> [f32; 2] {
+        let dim = self.dim;
+        self.dim += 1;
+        if dim >= self.order.len() || self.index >= self.cols * self.rows {
+            return random_2d(rng);
+        }
+        let cell = self.order[dim][self.index as usize];
+        let (col, row) = (cell % self.cols, cell / self.cols);
+        [((col as f32 + rng.gen::<f32>()) / self.cols as f32).min(ONE_MINUS_EPSILON),
+         ((row as f32 + rng.gen::<f32>()) / self.rows as f32).min(ONE_MINUS_EPSILON)]
+    }
+}
+
+// the largest f32 below 1
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON/2.0;
+
+// index's digits in base, mirrored about the radix point
+fn radical_inverse(base: u32, mut index: u32) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let (mut reversed, mut scale) = (0.0f64, inv_base);
+    while index > 0 {
+        reversed += (index % base) as f64 * scale;
+        index /= base;
+        scale *= inv_base;
+    }
+    (reversed as f32).min(ONE_MINUS_EPSILON)
+}
+
+// The Halton sequence, with a random (toroidal) shift per pixel and dimension
+// (Cranley-Patterson rotation) so every pixel gets its own copy.
+#[derive(Default)]
+pub struct HaltonSampler {
+    shifts: [[f32; 2]; 2],
+    index: u32,
+    dim: usize,
+}
+
+impl HaltonSampler {
+    const BASES: [[u32; 2]; 2] = [[2, 3], [5, 7]];
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel(&mut self, px: [u32; 2], rng: &mut dyn RngCore) {
+        for shift in self.shifts.iter_mut() {
+            *shift = random_2d(rng);
+        }
+    }
+
+    fn start_sample(&mut self, index: u32) {
+        self.index = index;
+        self.dim = 0;
+    }
+
+    fn get_2d(&mut self, rng: &mut dyn RngCore) -> [f32; 2] {
+        let dim = self.dim;
+        self.dim += 1;
+        if dim >= Self::BASES.len() {
+            return random_2d(rng);
+        }
+        let wrap = |x: f32| if x >= 1.0 { x - 1.0 } else { x };
+        let (bases, shift) = (Self::BASES[dim], self.shifts[dim]);
+        [wrap(radical_inverse(bases[0], self.index) + shift[0]).min(ONE_MINUS_EPSILON),
+         wrap(radical_inverse(bases[1], self.index) + shift[1]).min(ONE_MINUS_EPSILON)]
+    }
+}
+
+// Sobol points, Owen scrambled by hashing (Burley, "Practical Hash-based Owen Scrambling",
+// JCGT 2020): each digit of each coordinate gets randomly flipped in a way that keeps the
+// points as well spread out as they were. Every 2d pair uses the first two Sobol dimensions
+// (the best spread pair there is) with its own shuffle of the sample indices, rather than
+// going on to higher dimensions, so there's no running out of them.
+#[derive(Default)]
+pub struct SobolSampler {
+    seed: u32,
+    index: u32,
+    dim: u32,
+}
+
+// direction numbers for the first two Sobol dimensions, as 32 bit fractions: van der Corput,
+// then the primitive polynomial x + 1 with m_1 = 1 (Joe and Kuo's new-joe-kuo-6.21201)
+fn sobol_directions() -> &'static [[u32; 32]; 2] {
+    static DIRECTIONS: OnceLock<[[u32; 32]; 2]> = OnceLock::new();
+    DIRECTIONS.get_or_init(|| {
+        let mut m = 1u64;
+        let second = std::array::from_fn(|k| {
+            let v = (m << (31 - k)) as u32;
+            m ^= m << 1; // m_k = 2m_k-1 ^ m_k-1
+            v
+        });
+        [std::array::from_fn(|k| 1 << (31 - k)), second]
+    })
+}
+
+fn sobol(index: u32, dim: usize) -> u32 {
+    let v = &sobol_directions()[dim];
+    let (mut x, mut i, mut k) = (0u32, index, 0);
+    while i > 0 {
+        if i & 1 == 1 {
+            x ^= v[k];
+        }
+        i >>= 1;
+        k += 1;
+    }
+    x
+}
+
+// a random permutation of x's bits that only ever mixes lower bits into higher ones
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x ^= x.wrapping_mul(0x3d20adea);
+    x = x.wrapping_add(seed);
+    x = x.wrapping_mul((seed >> 16) | 1);
+    x ^= x.wrapping_mul(0x05526c56);
+    x ^= x.wrapping_mul(0x53a22864);
+    x
+}
+
+// ...so, mirrored, only higher bits into lower ones: an Owen scramble
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn hash_combine(seed: u32, v: u32) -> u32 {
+    seed ^ (v.wrapping_add(seed << 6).wrapping_add(seed >> 2))
+}
+
+fn to_unit(x: u32) -> f32 {
+    (x >> 8) as f32 / (1u32 << 24) as f32 // (24 bits is all an f32 holds)
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel(&mut self, px: [u32; 2], rng: &mut dyn RngCore) {
+        self.seed = rng.next_u32();
+    }
+
+    fn start_sample(&mut self, index: u32) {
+        self.index = index;
+        self.dim = 0;
+    }
+
+    fn get_2d(&mut self, rng: &mut dyn RngCore) -> [f32; 2] {
+        let seed = hash_combine(self.seed, self.dim);
+        self.dim += 1;
+        let index = nested_uniform_scramble(self.index, seed);
+        [to_unit(nested_uniform_scramble(sobol(index, 0), hash_combine(seed, 1))),
+         to_unit(nested_uniform_scramble(sobol(index, 1), hash_combine(seed, 2)))]
+    }
+}
+
+// Shirley and Chiu's concentric map from [0,1)² to the unit disc, which (unlike
+// polar coordinates) keeps nearby points nearby, so stratified points stay stratified
+pub fn concentric_disc(u: [f32; 2]) -> Vec2 {
+    let (a, b) = (2.0*u[0] - 1.0, 2.0*u[1] - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec2::new([0.0, 0.0]);
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, PI_4 * (b / a))
+    } else {
+        (b, PI_2 - PI_4 * (a / b))
+    };
+    Vec2::new([r * theta.cos(), r * theta.sin()])
+}
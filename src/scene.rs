@@ -6,7 +6,8 @@
 
 use crate::*;
 use crate::materials::*;
-use rand::{Rng, thread_rng};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 
 pub fn build_scene() -> Jumble {
     // the main stage
@@ -15,75 +16,76 @@ pub fn build_scene() -> Jumble {
 
 
     // materials //
-    let matgnd: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new([0.8, 0.8, 0.0])));
+    let matgnd: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([0.8, 0.8, 0.0])));
 
-    // let matctr: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new([0.7, 0.3, 0.3])));
-    //let matctr: Rc<dyn Material> = Rc::new(Transparent::new(Color::new([0.7, 0.3, 0.3]), 0.0, 1.5));
-    let matctrbook: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new([0.1, 0.2, 0.5])));
-    //let matctrbook: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new([1.0,1.0,1.0])));
-    let matctrbook: Rc<dyn Material> = Rc::new(Transparent::new(Color::new([1.0, 1.0, 1.0]), 0.0, 1.5));
+    // let matctr: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([0.7, 0.3, 0.3])));
+    //let matctr: Arc<dyn Material> = Arc::new(Transparent::new(Color::new([0.7, 0.3, 0.3]), 0.0, 1.5));
+    let matctrbook: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([0.1, 0.2, 0.5])));
+    //let matctrbook: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([1.0,1.0,1.0])));
+    let matctrbook: Arc<dyn Material> = Arc::new(Transparent::new(Color::new([1.0, 1.0, 1.0]), 0.0, 1.5));
 
-    //let matleft: Rc<dyn Material> = Rc::new(Transparent::new(Color::new([0.8, 0.8, 0.8]), 0.0, 1.5));
-    let matleft: Rc<dyn Material> = Rc::new(Shiny::new(Color::new([0.9, 0.9, 0.9]), 0.0));
-    //let matleft: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new([0.9, 0.3, 0.15])));
-    let matleftbook: Rc<dyn Material> = Rc::new(Transparent::new(Color::new([1.0, 1.0, 1.0]), 0.0, 1.5));
+    //let matleft: Arc<dyn Material> = Arc::new(Transparent::new(Color::new([0.8, 0.8, 0.8]), 0.0, 1.5));
+    let matleft: Arc<dyn Material> = Arc::new(Shiny::new(Color::new([0.9, 0.9, 0.9]), 0.0));
+    //let matleft: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([0.9, 0.3, 0.15])));
+    let matleftbook: Arc<dyn Material> = Arc::new(Transparent::new(Color::new([1.0, 1.0, 1.0]), 0.0, 1.5));
 
-    let matright: Rc<dyn Material> = Rc::new(Shiny::new(Color::new([0.8, 0.6, 0.2]), 0.0));
+    let matright: Arc<dyn Material> = Arc::new(Shiny::new(Color::new([0.8, 0.6, 0.2]), 0.0));
+    //let matright: Arc<dyn Material> = car_paint(Color::new([0.8, 0.6, 0.2]), 0.3);
 
 /*
     // verify vfov working (one of the best things of the book are its tests)
     let radius = PI_4.cos();
-    let matleft: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new([0.0, 0.0, 0.1])));
-    let matright: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new([1.0, 0.0, 0.0])));
-    let left: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([-radius,0.0,-1.0]), radius,
-                                                          Rc::clone(&matleft)));
-    let right: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([radius,0.0,-1.0]), radius,
-                                                           Rc::clone(&matright)));
-    scene.add(Rc::clone(&left));
-    scene.add(Rc::clone(&right));
+    let matleft: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([0.0, 0.0, 0.1])));
+    let matright: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([1.0, 0.0, 0.0])));
+    let left: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([-radius,0.0,-1.0]), radius,
+                                                          Arc::clone(&matleft)));
+    let right: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([radius,0.0,-1.0]), radius,
+                                                           Arc::clone(&matright)));
+    scene.add(Arc::clone(&left));
+    scene.add(Arc::clone(&right));
     return scene;
      */
 
     // instances of geometry
-    let gnd: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([0.0,-100.5,-1.0]), 100.0,
-                                                         Rc::clone(&matgnd)));
-    let ctr: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([0.0,0.0,-1.0]), 0.5,
-                                                         Rc::clone(&matctrbook)));
-    let sctr: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([0.0,0.0,0.0]), 0.5,
-                                                         Rc::clone(&matleft)));
-    let sctrout: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([0.0,0.0,0.0]), 0.5,
-                                                          Rc::clone(&matctrbook)));
-    let sctrin: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([0.0,0.0,0.0]), 0.45,
-                                                          Rc::clone(&matctrbook)));
-    let lout: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([-1.0,0.0,-1.0]), 0.5,
-                                                        Rc::clone(&matleftbook)));
-    let lin: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([-1.0,0.0,-1.0]), -0.45,
-                                                        Rc::clone(&matleftbook)));
-    let l: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([-1.0,0.5,-1.0]), 0.5,
-                                                       Rc::clone(&matleft)));
-    let lbig: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([-100.5,0.5,-1.0]), 100.0,
-                                                       Rc::clone(&matleft)));
-    let lbigout: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([-100.5,0.5,-1.0]), 100.0,
-                                                          Rc::clone(&matctrbook)));
-    let lbigin: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([-100.5,0.5,-1.0]), 90.0,
-                                                          Rc::clone(&matctrbook)));
-    let r: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([1.0,0.0,-1.0]), 0.5,
-                                                       Rc::clone(&matright)));
-    let sr: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([1.0,0.0,-0.5]), 0.5,
-                                                       Rc::clone(&matright)));
+    let gnd: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([0.0,-100.5,-1.0]), 100.0,
+                                                         Arc::clone(&matgnd)));
+    let ctr: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([0.0,0.0,-1.0]), 0.5,
+                                                         Arc::clone(&matctrbook)));
+    let sctr: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([0.0,0.0,0.0]), 0.5,
+                                                         Arc::clone(&matleft)));
+    let sctrout: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([0.0,0.0,0.0]), 0.5,
+                                                          Arc::clone(&matctrbook)));
+    let sctrin: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([0.0,0.0,0.0]), 0.45,
+                                                          Arc::clone(&matctrbook)));
+    let lout: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([-1.0,0.0,-1.0]), 0.5,
+                                                        Arc::clone(&matleftbook)));
+    let lin: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([-1.0,0.0,-1.0]), -0.45,
+                                                        Arc::clone(&matleftbook)));
+    let l: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([-1.0,0.5,-1.0]), 0.5,
+                                                       Arc::clone(&matleft)));
+    let lbig: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([-100.5,0.5,-1.0]), 100.0,
+                                                       Arc::clone(&matleft)));
+    let lbigout: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([-100.5,0.5,-1.0]), 100.0,
+                                                          Arc::clone(&matctrbook)));
+    let lbigin: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([-100.5,0.5,-1.0]), 90.0,
+                                                          Arc::clone(&matctrbook)));
+    let r: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([1.0,0.0,-1.0]), 0.5,
+                                                       Arc::clone(&matright)));
+    let sr: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([1.0,0.0,-0.5]), 0.5,
+                                                       Arc::clone(&matright)));
 
     let mut shiny_scene = Jumble::new();
     shiny_scene.name = "shiny".to_string();
-    //shiny_scene.add(Rc::clone(&ctr)); // center
-    shiny_scene.add(Rc::clone(&gnd)); // ground
-    //shiny_scene.add(Rc::clone(&lout)); // left outer
-    //shiny_scene.add(Rc::clone(&lin)); // left inner
-    shiny_scene.add(Rc::clone(&lbig)); // left mirror
-    // shiny_scene.add(Rc::clone(&lbigout)); left mirror
-    // shiny_scene.add(Rc::clone(&lbigin)); left mirror
-    //shiny_scene.add(Rc::clone(&l)); // left shiny
-    //shiny_scene.add(Rc::clone(&r)); // right
-    scene.add(Rc::new(shiny_scene) as Rc<dyn Intersectable>);
+    //shiny_scene.add(Arc::clone(&ctr)); // center
+    shiny_scene.add(Arc::clone(&gnd)); // ground
+    //shiny_scene.add(Arc::clone(&lout)); // left outer
+    //shiny_scene.add(Arc::clone(&lin)); // left inner
+    shiny_scene.add(Arc::clone(&lbig)); // left mirror
+    // shiny_scene.add(Arc::clone(&lbigout)); left mirror
+    // shiny_scene.add(Arc::clone(&lbigin)); left mirror
+    //shiny_scene.add(Arc::clone(&l)); // left shiny
+    //shiny_scene.add(Arc::clone(&r)); // right
+    scene.add(Arc::new(shiny_scene) as Arc<dyn Intersectable>);
 
     let mut squishy_scene = Jumble::new();
     squishy_scene.name = "squishy".to_string();
@@ -113,10 +115,11 @@ pub fn build_scene() -> Jumble {
     //println!("basis:\n{}", basis);
     // let csys = CoordSys::new(Vec3::zero(), Vec3::new([1.0, 1.0, 1.0]),
     //                          basis.u(), basis.v(), basis.w());
-    //let csys = CoordSys::from_matrix(basis.generic_inverse()); // works for geometry
-    //let csys = CoordSys::from_matrix(basis.transpose()); // horribly fails with rot*scale*trans
+    //let csys = CoordSys::from_matrix(basis).unwrap(); // (any order; see Transform for an easier way)
     mat = rotate;
-    println!("mat:\n{}", mat);
+    if debug() {
+        println!("mat:\n{}", mat);
+    }
     let csys = CoordSys::new(
         Vec3::new([1.0, 0.5, -1.0]),  // origin
         //Vec3::new([0.0, 0.0, 0.0]),  // origin
@@ -126,67 +129,43 @@ pub fn build_scene() -> Jumble {
         Vec3::new([1.0, 1.0, 1.0]),  // scale
         Vec3::new([1.0, 0.5, 0.0]),  // u
         Vec3::new([0.0, 1.0, 0.0]),  // v
-        Vec3::new([0.0, 0.0, 1.0])); // w
-        //mat.u(), mat.v(), mat.w());
+        Vec3::new([0.0, 0.0, 1.0])) // w
+        //mat.u(), mat.v(), mat.w())
+        .unwrap();
     squishy_scene.set_csys(csys);
-    squishy_scene.add(Rc::clone(&sctr));
-    // squishy_scene.add(Rc::clone(&sctrout)); // problems abound with transparents
-    // squishy_scene.add(Rc::clone(&sctrin));
-    squishy_scene.add(Rc::clone(&sr));
-    scene.add(Rc::new(squishy_scene) as Rc<dyn Intersectable>);
+    squishy_scene.add(Arc::clone(&sctr));
+    // squishy_scene.add(Arc::clone(&sctrout)); // problems abound with transparents
+    // squishy_scene.add(Arc::clone(&sctrin));
+    squishy_scene.add(Arc::clone(&sr));
+    scene.add(Arc::new(squishy_scene) as Arc<dyn Intersectable>);
 
 
     let mut sq2 = Jumble::new();
     sq2.name = "sq2".to_string();
 
-    let rotate = Matrix::rotation(-3.0*PI_4, Axis::Z);
-    //let rotate = Matrix::rotation(-PI_4, Axis::Y);
-    //let rotate = Matrix::rotation(-PI_4, Axis::X);
-    if crate::DEBUG {
-        //println!("rotate:\n {}", rotate);
-    }
-
-    let scale = Matrix::scale(Vec3::new([0.5, 1.25, 1.0]));
-    if crate::DEBUG {
-        //println!("scale:\n {}", scale);
-    }
-
-    let translate = Matrix::translation(Vec3::new([-1.25, 0.25, 0.0]));
-    if crate::DEBUG {
-        //println!("csys:\n{}", csys);
-    }
-    let basis = scale * rotate * translate;
-    let csys = CoordSys::new(Vec3::zero(), Vec3::new([1.0, 1.0, 1.0]),
-                             basis.u(), basis.v(), basis.w());
+    let csys = Transform::new()
+        .scale(Vec3::new([0.5, 1.25, 1.0]))
+        .rotate(-3.0*PI_4, Vec3::new([0.0, 0.0, 1.0]))
+        //.rotate(-PI_4, Vec3::new([0.0, 1.0, 0.0]))
+        //.rotate(-PI_4, Vec3::new([1.0, 0.0, 0.0]))
+        .translate(Vec3::new([-1.25, 0.25, 0.0]))
+        .build().unwrap();
     sq2.set_csys(csys);
-    sq2.add(Rc::clone(&ctr));
-    //scene.add(Rc::new(sq2) as Rc<dyn Intersectable>);
+    sq2.add(Arc::clone(&ctr));
+    //scene.add(Arc::new(sq2) as Arc<dyn Intersectable>);
 
 
     let mut sq3 = Jumble::new();
     sq3.name = "sq3".to_string();
-    let csys = Matrix::identity();
-
-    let rotate = Matrix::rotation(-3.0*PI_4, Axis::Z) * Matrix::rotation(-PI_2, Axis::X);
-    if crate::DEBUG {
-        //println!("rotate:\n {}", rotate);
-    }
-
-    let scale = Matrix::scale(Vec3::new([0.5, 1.0, 1.1]));
-    if crate::DEBUG {
-        //println!("scale:\n {}", scale);
-    }
-
-    let translate = Matrix::translation(Vec3::new([1.25,-0.333,-0.25]));
-    if crate::DEBUG {
-        //println!("csys:\n {}", csys);
-    }
-    let basis = scale * rotate * translate;
-    let csys = CoordSys::new(Vec3::zero(), Vec3::new([1.0, 1.0, 1.0]),
-                             basis.u(), basis.v(), basis.w());
+    let csys = Transform::new()
+        .scale(Vec3::new([0.5, 1.0, 1.1]))
+        .rotate(-PI_2, Vec3::new([1.0, 0.0, 0.0]))
+        .rotate(-3.0*PI_4, Vec3::new([0.0, 0.0, 1.0]))
+        .translate(Vec3::new([1.25, -0.333, -0.25]))
+        .build().unwrap();
     sq3.set_csys(csys);
-    sq3.add(Rc::clone(&ctr));
-    //scene.add(Rc::new(sq3) as Rc<dyn Intersectable>);
+    sq3.add(Arc::clone(&ctr));
+    //scene.add(Arc::new(sq3) as Arc<dyn Intersectable>);
 
 
 
@@ -197,37 +176,38 @@ pub fn build_scene() -> Jumble {
     // fov_test_scene.name = "fov_test".to_string();
     // let radius = (std::f32::consts::PI / 4.0).cos();
     // NOTE: two ways to declare the same type (the book teaches the first)
-    // let sl: Rc<dyn Intersectable> = Rc::new(Sphere::new(Vec3::new([-radius,0.0,-1.0]), radius));
-    // let sr = Rc::new(Sphere::new(Vec3::new([radius,0.0,-1.0]), radius)) as Rc<dyn Intersectable>;
-    // fov_test_scene.add(Rc::clone(&sl));
-    // fov_test_scene.add(Rc::clone(&sr));
-    //scene.add(Rc::new(fov_test_scene) as Rc<dyn Intersectable>);
+    // let sl: Arc<dyn Intersectable> = Arc::new(Sphere::new(Vec3::new([-radius,0.0,-1.0]), radius));
+    // let sr = Arc::new(Sphere::new(Vec3::new([radius,0.0,-1.0]), radius)) as Arc<dyn Intersectable>;
+    // fov_test_scene.add(Arc::clone(&sl));
+    // fov_test_scene.add(Arc::clone(&sr));
+    //scene.add(Arc::new(fov_test_scene) as Arc<dyn Intersectable>);
 ////////////////////////////////////////////////////////////////////////////////////////////
     // let mut sub_scene = Jumble::new();
     // sub_scene.name = "sub".to_string();
-    // sub_scene.add(Rc::clone(&ctr));
-    // sub_scene.add(Rc::clone(&gnd));
-    //scene.add(Rc::new(sub_scene) as Rc<dyn Intersectable>);
+    // sub_scene.add(Arc::clone(&ctr));
+    // sub_scene.add(Arc::clone(&gnd));
+    //scene.add(Arc::new(sub_scene) as Arc<dyn Intersectable>);
 ////////////////////////////////////////////////////////////////////////////////////////////
 
 
     scene
 }
 
-pub fn build_rtiow_final_scene() -> Jumble {
+// (the marbles are scattered by seed, so the same seed gets the same scene)
+pub fn build_rtiow_final_scene(seed: u64) -> Jumble {
     // the main stage
     let mut scene = Jumble::new();
     scene.name = "main".to_string();
 
     // rng
-    let mut rng = rand::thread_rng();
+    let mut rng = StdRng::seed_from_u64(seed);
 
     // glass
-    let glass: Rc<dyn Material> = Rc::new(Transparent::new(Color::new([1.0, 1.0, 1.0]), 0.0, 1.5));
+    let glass: Arc<dyn Material> = Arc::new(Transparent::new(Color::new([1.0, 1.0, 1.0]), 0.0, 1.5));
 
     // ground
-    let matgnd: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new([0.5, 0.5, 0.5])));
-    scene.add(Rc::new(Sphere::new(Vec3::new([0.0,-1000.0,0.0]), 1000.0, Rc::clone(&matgnd))));
+    let matgnd: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([0.5, 0.5, 0.5])));
+    scene.add(Arc::new(Sphere::new(Vec3::new([0.0,-1000.0,0.0]), 1000.0, Arc::clone(&matgnd))));
 
     // marbles
     for a in -11..11 {
@@ -240,31 +220,73 @@ pub fn build_rtiow_final_scene() -> Jumble {
             if (center - Vec3::new([4.0, 0.2, 0.0])).len() > 0.9 {
                 if choose_mat < 0.8 {
                     // diffuse
-                    let albedo = Color::rand() * Color::rand();
-                    let mat: Rc<dyn Material> = Rc::new(Lambertian::new(albedo));
-                    scene.add(Rc::new(Sphere::new(center, 0.2, Rc::clone(&mat))));
+                    let albedo = Color::rand(&mut rng) * Color::rand(&mut rng);
+                    let mat: Arc<dyn Material> = Arc::new(Lambertian::new(albedo));
+                    scene.add(Arc::new(Sphere::new(center, 0.2, Arc::clone(&mat))));
                 } else if choose_mat < 0.95 {
                     // metal
-                    let albedo = Color::rand() / 2.0 + Color::new([0.5, 0.5, 0.5]);
+                    let albedo = Color::rand(&mut rng) / 2.0 + Color::new([0.5, 0.5, 0.5]);
                     let fuzz = rng.gen_range(0.0..=0.5);
-                    let mat: Rc<dyn Material> = Rc::new(Shiny::new(albedo, fuzz));
-                    scene.add(Rc::new(Sphere::new(center, 0.2, Rc::clone(&mat))));
+                    let mat: Arc<dyn Material> = Arc::new(Shiny::new(albedo, fuzz));
+                    scene.add(Arc::new(Sphere::new(center, 0.2, Arc::clone(&mat))));
                 } else {
                     // glass
-                    scene.add(Rc::new(Sphere::new(center, 0.2, Rc::clone(&glass))));
+                    scene.add(Arc::new(Sphere::new(center, 0.2, Arc::clone(&glass))));
                 }
             }
         }
     }
 
     // boulders
-    scene.add(Rc::new(Sphere::new(Vec3::new([0.0, 1.0, 0.0]), 1.0, Rc::clone(&glass))));
+    scene.add(Arc::new(Sphere::new(Vec3::new([0.0, 1.0, 0.0]), 1.0, Arc::clone(&glass))));
 
-    let mat: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new([0.4, 0.2, 0.1])));
-    scene.add(Rc::new(Sphere::new(Vec3::new([-4.0, 1.0, 0.0]), 1.0, Rc::clone(&mat))));
+    let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new([0.4, 0.2, 0.1])));
+    scene.add(Arc::new(Sphere::new(Vec3::new([-4.0, 1.0, 0.0]), 1.0, Arc::clone(&mat))));
 
-    let mat: Rc<dyn Material> = Rc::new(Shiny::new(Color::new([0.4, 0.2, 0.1]), 0.0));
-    scene.add(Rc::new(Sphere::new(Vec3::new([4.0, 1.0, 0.0]), 1.0, Rc::clone(&mat))));
+    let mat: Arc<dyn Material> = Arc::new(Shiny::new(Color::new([0.4, 0.2, 0.1]), 0.0));
+    scene.add(Arc::new(Sphere::new(Vec3::new([4.0, 1.0, 0.0]), 1.0, Arc::clone(&mat))));
 
     return scene;
 }
+
+// principled materials for the usual suspects (tweak the knobs from there)
+
+pub fn principled(base: Color, metallic: f32, roughness: f32) -> Arc<dyn Material> {
+    let mut mat = Principled::new(base);
+    mat.metallic = metallic;
+    mat.roughness = roughness;
+    Arc::new(mat)
+}
+
+pub fn plastic(base: Color, roughness: f32) -> Arc<dyn Material> {
+    principled(base, 0.0, roughness)
+}
+
+pub fn metal(base: Color, roughness: f32) -> Arc<dyn Material> {
+    principled(base, 1.0, roughness)
+}
+
+pub fn glass(tint: Color, roughness: f32, eta: f32) -> Arc<dyn Material> {
+    let mut mat = Principled::new(tint);
+    mat.roughness = roughness;
+    mat.transmission = 1.0;
+    mat.eta = eta;
+    Arc::new(mat)
+}
+
+// a (rough) colored base under a glossy varnish
+pub fn car_paint(base: Color, roughness: f32) -> Arc<dyn Material> {
+    let mut mat = Principled::new(base);
+    mat.metallic = 0.5;
+    mat.roughness = roughness;
+    mat.clearcoat = 1.0;
+    Arc::new(mat)
+}
+
+// velvet, felt and friends: diffuse with a soft glow at the edges
+pub fn cloth(base: Color) -> Arc<dyn Material> {
+    let mut mat = Principled::new(base);
+    mat.specular = 0.0;
+    mat.sheen = 1.0;
+    Arc::new(mat)
+}
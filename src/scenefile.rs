@@ -0,0 +1,609 @@
+//
+// scene description files
+//
+// A little text format so scenes can be tweaked without recompiling:
+//
+//   # comments run to the end of the line
+//   settings { spp 100 max-depth 50 aspect 16/9 background black output "/tmp/shiny.png" }
+//   camera { from 3 1.75 1.25  at 1.1 0.85 -0.75  up 0 1 0  fov 40  aperture 0.001 }
+//
+//   texture checks checker 0.5  0.2 0.3 0.1  0.9 0.9 0.9   # cube size, then two colors (or textures)
+//   texture earth image "earthmap.png"               # relative to the scene file, wrapped on by uv
+//   texture veins marble 4  0.9 0.9 0.8              # noise|turbulence|marble scale [color]
+//
+//   material ground lambertian 0.8 0.8 0.0           # albedo
+//   material floor lambertian checks                 # (anywhere a color goes, a texture can too)
+//   material mirror shiny 0.9 0.9 0.9 0.0            # albedo fuzz
+//   material glass transparent 1.0 1.0 1.0 0.0 1.5   # albedo fuzz eta [absorb r g b (per unit distance)]
+//   material gold conductor gold 0.2                 # gold|copper|aluminium or η(rgb) k(rgb), then roughness
+//   material frosted dielectric 1.5 0.3              # eta roughness [absorb r g b]
+//   material lamp light 4 4 4 [two-sided]            # emitted color (can be > 1)
+//   material paint principled { base 0.8 0.1 0.1  metallic 0.5  roughness 0.3  clearcoat 1 }
+//                                                    # also specular, transmission, clearcoat-roughness,
+//                                                    # sheen, sheen-tint and eta (see principled.rs)
+//
+//   sphere 0 -100.5 -1  100  ground                  # center radius material
+//   triangle -1 0 -2  1 0 -2  0 1.5 -2  mirror       # three points and a material
+//   obj "models/teapot.obj"                          # relative to the scene file, see obj.rs
+//
+//   jumble squishy {                                 # name is optional
+//       origin 1 0.5 -1   scale 1 1 1                # CoordSys, all optional
+//       u 1 0.5 0   v 0 1 0   w 0 0 1
+//       sphere 0 0 0  0.5  mirror
+//       jumble { ... }                               # jumbles nest
+//   }
+//
+// The top level of the file is itself the body of the "main" Jumble.
+// Textures and materials must be defined before they're used and are visible everywhere after.
+// The settings block takes the same names as the command line's long options
+// (minus the --) and the command line wins when both are given.
+//
+
+use crate::*;
+use crate::materials::*;
+use crate::settings::RenderSettings;
+use crate::textures::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub struct SceneError {
+    pub line: usize, // 1-based, 0 if the error isn't about any particular spot
+    pub col: usize,
+    pub msg: String,
+}
+
+impl SceneError {
+    // path:line:col: msg, the way compilers do it
+    pub fn in_file(&self, path: &str) -> String {
+        if self.line == 0 { self.msg.clone() } else { format!("{}:{}", path, self) }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.msg)
+        } else {
+            write!(f, "{}:{}: {}", self.line, self.col, self.msg)
+        }
+    }
+}
+
+// everything needed to set up a render from a scene file
+pub struct SceneFile {
+    pub scene: Jumble,
+    pub camera: CameraDesc,
+    pub files: Vec<PathBuf>,       // everything the scene was read from (it first, if load read it)
+    settings: Vec<(Token, Token)>, // key, value
+}
+
+impl SceneFile {
+    // apply the file's settings block (best done after presets, before the command line)
+    pub fn apply_settings(&self, settings: &mut RenderSettings) -> Result<(), SceneError> {
+        for (key, val) in &self.settings {
+            settings.set(&key.text, &val.text).map_err(|e| val.error(e))?;
+        }
+        Ok(())
+    }
+}
+
+// camera parameters (defaults match main.rs's setup_camera)
+#[derive(Debug, Clone, Copy)]
+pub struct CameraDesc {
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    pub vup: Vec3,
+    pub vfov: f32,
+    pub aperture: f32,
+    pub focus: Option<f32>, // distance to focal plane, defaults to |look_at - look_from|
+}
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        Self {
+            look_from: Vec3::new([3.0, 1.75, 1.25]),
+            look_at: Vec3::new([1.1, 0.85, -0.75]),
+            vup: Vec3::new([0.0, 1.0, 0.0]),
+            vfov: 40.0,
+            aperture: 0.001,
+            focus: None,
+        }
+    }
+}
+
+impl CameraDesc {
+    pub fn build(&self, settings: &RenderSettings) -> Camera {
+        let dist_to_focus = self.focus.unwrap_or((self.look_at - self.look_from).len());
+        Camera::init(settings, self.aperture, self.vfov,
+                     self.look_from, self.look_at, self.vup, dist_to_focus)
+    }
+}
+
+pub fn load(path: &str) -> Result<SceneFile, SceneError> {
+    let src = std::fs::read_to_string(path).map_err(|e| SceneError {
+        line: 0, col: 0, msg: format!("can't read {}: {}", path, e),
+    })?;
+    let mut file = parse(&src, Path::new(path).parent().unwrap_or_else(|| Path::new("")))?;
+    file.files.insert(0, PathBuf::from(path));
+    Ok(file)
+}
+
+// dir is where the files the scene refers to (e.g., objs) are relative to
+pub fn parse(src: &str, dir: &Path) -> Result<SceneFile, SceneError> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        dir: dir.to_path_buf(),
+        eof: eof_position(src),
+        materials: HashMap::new(),
+        textures: HashMap::new(),
+        camera: CameraDesc::default(),
+        settings: Vec::new(),
+        files: Vec::new(),
+    };
+
+    let mut scene = Jumble::new();
+    scene.name = "main".to_string();
+    parser.jumble_body(&mut scene, true)?;
+
+    Ok(SceneFile { scene, camera: parser.camera, files: parser.files, settings: parser.settings })
+}
+
+//
+// tokenizer
+//
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    line: usize,
+    col: usize,
+}
+
+impl Token {
+    fn error(&self, msg: String) -> SceneError {
+        SceneError { line: self.line, col: self.col, msg }
+    }
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, SceneError> {
+    let mut tokens = Vec::new();
+    for (l, text) in src.lines().enumerate() {
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let line = l + 1;
+            let col = text[..i].chars().count() + 1;
+            match c {
+                '#' => break,
+                c if c.is_whitespace() => {},
+                '{' | '}' => tokens.push(Token { text: c.to_string(), line, col }),
+                '"' => {
+                    let mut s = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '"')) => break,
+                            Some((_, c)) => s.push(c),
+                            None => return Err(SceneError { line, col, msg: "unterminated string".to_string() }),
+                        }
+                    }
+                    tokens.push(Token { text: s, line, col });
+                },
+                _ => {
+                    let mut s = c.to_string();
+                    while let Some(&(_, c)) = chars.peek() {
+                        if c.is_whitespace() || c == '{' || c == '}' || c == '#' || c == '"' {
+                            break;
+                        }
+                        s.push(c);
+                        chars.next();
+                    }
+                    tokens.push(Token { text: s, line, col });
+                },
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// where "unexpected end of file" errors point
+fn eof_position(src: &str) -> (usize, usize) {
+    let line = src.lines().count().max(1);
+    let col = src.lines().last().map_or(0, |l| l.chars().count()) + 1;
+    (line, col)
+}
+
+//
+// parser
+//
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    dir: PathBuf,
+    eof: (usize, usize),
+    materials: HashMap<String, Arc<dyn Material>>,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    camera: CameraDesc,
+    settings: Vec<(Token, Token)>,
+    files: Vec<PathBuf>, // read so far (objs, mtls, textures)
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self, what: &str) -> Result<Token, SceneError> {
+        match self.tokens.get(self.pos) {
+            Some(tok) => {
+                self.pos += 1;
+                Ok(tok.clone())
+            },
+            None => Err(self.eof_error(what)),
+        }
+    }
+
+    fn eof_error(&self, what: &str) -> SceneError {
+        SceneError { line: self.eof.0, col: self.eof.1, msg: format!("expected {}, found end of file", what) }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, SceneError> {
+        let tok = self.next(&format!("'{}'", text))?;
+        if tok.text != text {
+            return Err(tok.error(format!("expected '{}', found '{}'", text, tok.text)));
+        }
+        Ok(tok)
+    }
+
+    fn number(&mut self, what: &str) -> Result<f32, SceneError> {
+        let tok = self.next(what)?;
+        match tok.text.parse::<f32>() {
+            Ok(n) if n.is_finite() => Ok(n),
+            _ => Err(tok.error(format!("expected {} (a number), found '{}'", what, tok.text))),
+        }
+    }
+
+    fn vec3(&mut self, what: &str) -> Result<Vec3, SceneError> {
+        Ok(Vec3::new([self.number(what)?, self.number(what)?, self.number(what)?]))
+    }
+
+    fn color(&mut self) -> Result<Color, SceneError> {
+        Ok(Color::new([self.number("red")?, self.number("green")?, self.number("blue")?]))
+    }
+
+    fn next_is_number(&self) -> bool {
+        self.peek().is_some_and(|t| t.text.parse::<f32>().is_ok())
+    }
+
+    // r g b or the name of a texture
+    fn texture_ref(&mut self) -> Result<Arc<dyn Texture>, SceneError> {
+        if self.next_is_number() {
+            return Ok(Arc::new(SolidColor::new(self.color()?)));
+        }
+        let name = self.next("color or texture name")?;
+        let tex = self.textures.get(&name.text)
+            .ok_or_else(|| name.error(format!("undefined texture '{}'", name.text)))?;
+        Ok(Arc::clone(tex))
+    }
+
+    fn texture(&mut self) -> Result<(), SceneError> {
+        let name = self.next("texture name")?;
+        let kind = self.next("texture type")?;
+        let tex: Arc<dyn Texture> = match kind.text.as_str() {
+            "solid" => Arc::new(SolidColor::new(self.color()?)),
+            "checker" => {
+                let size = self.number("checker size")?;
+                if size <= 0.0 {
+                    return Err(kind.error(format!("checker size must be positive, not {}", size)));
+                }
+                Arc::new(Checker::new(size, self.texture_ref()?, self.texture_ref()?))
+            },
+            "image" => {
+                let file = self.next("image file name")?;
+                let path = self.dir.join(&file.text);
+                let tex = ImageTexture::load_png(&path.to_string_lossy()).map_err(|e| file.error(e))?;
+                self.files.push(path);
+                Arc::new(tex)
+            },
+            "noise" | "turbulence" | "marble" => {
+                let noise = match kind.text.as_str() {
+                    "noise" => NoiseKind::Smooth,
+                    "turbulence" => NoiseKind::Turbulence,
+                    _ => NoiseKind::Marble,
+                };
+                let scale = self.number("noise scale")?;
+                let color = if self.next_is_number() { self.color()? } else { Color::white() };
+                Arc::new(NoiseTexture::new(noise, scale, color))
+            },
+            _ => return Err(kind.error(format!("unknown texture type '{}' (try solid, checker, image, noise, turbulence or marble)",
+                                               kind.text))),
+        };
+        self.textures.insert(name.text, tex);
+        Ok(())
+    }
+
+    // statements allowed inside a jumble (or at the top level, which also allows settings and camera)
+    fn jumble_body(&mut self, jumble: &mut Jumble, top: bool) -> Result<(), SceneError> {
+        let (mut origin, mut scale) = (Vec3::zero(), Vec3::new([1.0, 1.0, 1.0]));
+        let (mut u, mut v, mut w) = (Vec3::new([1.0, 0.0, 0.0]),
+                                     Vec3::new([0.0, 1.0, 0.0]),
+                                     Vec3::new([0.0, 0.0, 1.0]));
+        let mut csys_tok = None; // (the first csys statement, for errors)
+
+        loop {
+            let tok = match self.peek() {
+                None if top => break,
+                None => return Err(self.eof_error("'}'")),
+                Some(tok) if tok.text == "}" && !top => { self.pos += 1; break; },
+                Some(tok) => tok.clone(),
+            };
+            self.pos += 1;
+            match tok.text.as_str() {
+                "origin" => { origin = self.vec3("origin")?; csys_tok.get_or_insert(tok); },
+                "scale" => {
+                    scale = self.vec3("scale")?;
+                    if scale.x() == 0.0 || scale.y() == 0.0 || scale.z() == 0.0 {
+                        return Err(tok.error("scale can't be zero".to_string()));
+                    }
+                    csys_tok.get_or_insert(tok);
+                },
+                "u" => { u = self.vec3("u")?; csys_tok.get_or_insert(tok); },
+                "v" => { v = self.vec3("v")?; csys_tok.get_or_insert(tok); },
+                "w" => { w = self.vec3("w")?; csys_tok.get_or_insert(tok); },
+                "material" => self.material()?,
+                "texture" => self.texture()?,
+                "sphere" => jumble.add(self.sphere()?),
+                "triangle" => jumble.add(self.triangle()?),
+                "obj" => {
+                    let file = self.next("obj file name")?;
+                    let path = self.dir.join(&file.text);
+                    let obj = obj::load_obj(&path.to_string_lossy(), &mut self.files)
+                        .map_err(|e| file.error(e.to_string()))?;
+                    jumble.add(Arc::new(obj));
+                },
+                "jumble" => {
+                    let mut child = Jumble::new();
+                    if let Some(name) = self.peek() {
+                        if name.text != "{" {
+                            child.name = self.next("jumble name")?.text;
+                        }
+                    }
+                    self.expect("{")?;
+                    self.jumble_body(&mut child, false)?;
+                    jumble.add(Arc::new(child));
+                },
+                "settings" if top => self.settings()?,
+                "camera" if top => self.camera()?,
+                "settings" | "camera" => return Err(tok.error(format!("{} only allowed at the top level", tok.text))),
+                _ => return Err(tok.error(format!("unexpected '{}'", tok.text))),
+            }
+        }
+
+        if let Some(tok) = csys_tok {
+            jumble.set_csys(CoordSys::new(origin, scale, u, v, w)
+                            .map_err(|e| tok.error(format!("bad coordinate system: {}", e)))?);
+        }
+        Ok(())
+    }
+
+    fn material(&mut self) -> Result<(), SceneError> {
+        let name = self.next("material name")?;
+        let kind = self.next("material type")?;
+        let mat: Arc<dyn Material> = match kind.text.as_str() {
+            "lambertian" => Arc::new(Lambertian::textured(self.texture_ref()?)),
+            "shiny" => Arc::new(Shiny::textured(self.texture_ref()?, self.number("fuzz")?)),
+            "transparent" => {
+                let glass = Transparent::textured(self.texture_ref()?, self.number("fuzz")?, self.number("eta")?);
+                if self.peek().is_some_and(|t| t.text == "absorb") {
+                    self.pos += 1;
+                    Arc::new(glass.with_absorption(self.color()?))
+                } else {
+                    Arc::new(glass)
+                }
+            },
+            "conductor" => {
+                if self.next_is_number() {
+                    let (eta, k) = (self.color()?, self.color()?);
+                    Arc::new(Conductor::new(eta, k, self.number("roughness")?))
+                } else {
+                    let preset = self.next("metal (gold, copper or aluminium) or η and k")?;
+                    let roughness = self.number("roughness")?;
+                    Arc::new(Conductor::preset(&preset.text, roughness)
+                             .ok_or_else(|| preset.error(format!("unknown metal '{}' (try gold, copper or aluminium)", preset.text)))?)
+                }
+            },
+            "dielectric" => {
+                let glass = Dielectric::new(self.number("eta")?, self.number("roughness")?);
+                if self.peek().is_some_and(|t| t.text == "absorb") {
+                    self.pos += 1;
+                    Arc::new(glass.with_absorption(self.color()?))
+                } else {
+                    Arc::new(glass)
+                }
+            },
+            "light" => {
+                let light = DiffuseLight::textured(self.texture_ref()?);
+                if self.peek().is_some_and(|t| t.text == "two-sided") {
+                    self.pos += 1;
+                    Arc::new(light.two_sided())
+                } else {
+                    Arc::new(light)
+                }
+            },
+            "principled" => Arc::new(self.principled()?),
+            _ => return Err(kind.error(format!("unknown material type '{}' (try lambertian, shiny, transparent, conductor, dielectric, principled or light)",
+                                               kind.text))),
+        };
+        self.materials.insert(name.text, mat);
+        Ok(())
+    }
+
+    // { knob value ... } (any left out keep Principled's defaults)
+    fn principled(&mut self) -> Result<Principled, SceneError> {
+        self.expect("{")?;
+        let mut mat = Principled::new(Color::new([0.8, 0.8, 0.8]));
+        loop {
+            let key = self.next("principled parameter or '}'")?;
+            match key.text.as_str() {
+                "}" => break,
+                "base" => mat.base_color = self.texture_ref()?,
+                "metallic" => mat.metallic = self.number("metallic")?,
+                "roughness" => mat.roughness = self.number("roughness")?,
+                "specular" => mat.specular = self.number("specular")?,
+                "transmission" => mat.transmission = self.number("transmission")?,
+                "clearcoat" => mat.clearcoat = self.number("clearcoat")?,
+                "clearcoat-roughness" => mat.clearcoat_roughness = self.number("clearcoat-roughness")?,
+                "sheen" => mat.sheen = self.number("sheen")?,
+                "sheen-tint" => mat.sheen_tint = self.number("sheen-tint")?,
+                "eta" => mat.eta = self.number("eta")?,
+                _ => return Err(key.error(format!("unknown principled parameter '{}'", key.text))),
+            }
+        }
+        Ok(mat)
+    }
+
+    fn material_ref(&mut self) -> Result<Arc<dyn Material>, SceneError> {
+        let name = self.next("material name")?;
+        let mat = self.materials.get(&name.text)
+            .ok_or_else(|| name.error(format!("undefined material '{}'", name.text)))?;
+        Ok(Arc::clone(mat))
+    }
+
+    fn sphere(&mut self) -> Result<Arc<dyn Intersectable>, SceneError> {
+        let center = self.vec3("center")?;
+        let radius = self.number("radius")?;
+        Ok(Arc::new(Sphere::new(center, radius, self.material_ref()?)))
+    }
+
+    fn triangle(&mut self) -> Result<Arc<dyn Intersectable>, SceneError> {
+        let (p0, p1, p2) = (self.vec3("point")?, self.vec3("point")?, self.vec3("point")?);
+        Ok(Arc::new(Triangle::new(p0, p1, p2, self.material_ref()?)))
+    }
+
+    fn settings(&mut self) -> Result<(), SceneError> {
+        self.expect("{")?;
+        loop {
+            let key = self.next("setting or '}'")?;
+            if key.text == "}" {
+                break;
+            }
+            if !RenderSettings::KEYS.contains(&key.text.as_str()) {
+                return Err(key.error(format!("unknown setting '{}'", key.text)));
+            }
+            if key.text == "scene" {
+                return Err(key.error("a scene file can't choose a different scene".to_string()));
+            }
+            let val = self.next(&format!("value for {}", key.text))?;
+            self.settings.push((key, val));
+        }
+        Ok(())
+    }
+
+    fn camera(&mut self) -> Result<(), SceneError> {
+        self.expect("{")?;
+        loop {
+            let key = self.next("camera parameter or '}'")?;
+            match key.text.as_str() {
+                "}" => break,
+                "from" => self.camera.look_from = self.vec3("from")?,
+                "at" => self.camera.look_at = self.vec3("at")?,
+                "up" => self.camera.vup = self.vec3("up")?,
+                "fov" => self.camera.vfov = self.number("fov")?,
+                "aperture" => self.camera.aperture = self.number("aperture")?,
+                "focus" => self.camera.focus = Some(self.number("focus")?),
+                _ => return Err(key.error(format!("unknown camera parameter '{}'", key.text))),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(src: &str) -> SceneError {
+        match parse(src, Path::new("")) {
+            Ok(_) => panic!("parsed fine: {:?}", src),
+            Err(e) => e,
+        }
+    }
+
+    fn assert_error(src: &str, line: usize, col: usize, msg: &str) {
+        let e = error(src);
+        assert_eq!((e.line, e.col, e.msg.as_str()), (line, col, msg), "for {:?}", src);
+    }
+
+    #[test]
+    fn unterminated_string() {
+        assert_error("# textures\ntexture t image \"earth.png", 2, 17, "unterminated string");
+    }
+
+    #[test]
+    fn end_of_file() {
+        assert_error("sphere 0 0 0", 1, 13, "expected radius, found end of file");
+        assert_error("material m lambertian 0.5 0.5\n", 1, 30, "expected blue, found end of file");
+        assert_error("jumble {\n  jumble {", 2, 11, "expected '}', found end of file");
+    }
+
+    #[test]
+    fn undefined_names() {
+        assert_error("material m lambertian 0.5 0.5 0.5\nsphere 0 0 0  1  n", 2, 18, "undefined material 'n'");
+        assert_error("material m lambertian checks", 1, 23, "undefined texture 'checks'");
+    }
+
+    #[test]
+    fn misplaced_and_mistyped() {
+        assert_error("jumble {\n  settings { spp 4 }\n}", 2, 3, "settings only allowed at the top level");
+        assert_error("jumble a { jumble b { camera { fov 30 } } }", 1, 23, "camera only allowed at the top level");
+        assert_error("sphere 0 0 x  1  m", 1, 12, "expected center (a number), found 'x'");
+        assert_error("  cube 1", 1, 3, "unexpected 'cube'");
+    }
+
+    #[test]
+    fn nested_csys() {
+        // u and v the same way: the error points at the first coordinate system word
+        let src = "jumble a {\n  jumble b {\n    origin 1 0 0\n    u 1 0 0  v 2 0 0  w 0 0 1\n  }\n}";
+        assert_error(src, 3, 5, &format!("bad coordinate system: {}", MatrixError::Singular));
+        assert_error("jumble {\n  jumble {\n    scale 1 0 1\n  }\n}", 3, 5, "scale can't be zero");
+    }
+
+    #[test]
+    fn a_scene() {
+        let src = "
+            settings { spp 7 }
+            camera { from 0 1 2  at 0 0 -1  fov 30 }
+            material m lambertian 0.5 0.5 0.5
+            sphere 0 0 -1  0.5  m
+            triangle -1 0 -2  1 0 -2  0 1.5 -2  m
+            jumble inner {      # moved up 2
+                origin 0 2 0
+                sphere 0 0 0  1  m
+            }";
+        let file = match parse(src, Path::new("")) {
+            Ok(file) => file,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(file.scene.name, "main");
+        assert_eq!(file.scene.len(), 3);
+        let bbox = file.scene.bbox();
+        for (got, want) in [(bbox.min, Vec3::new([-1.0, -0.5, -2.0])), (bbox.max, Vec3::new([1.0, 3.0, 1.0]))] {
+            assert!((got - want).len() < 1e-5, "bbox {:?}", bbox);
+        }
+
+        let camera = file.camera;
+        assert_eq!(camera.look_from, Vec3::new([0.0, 1.0, 2.0]));
+        assert_eq!(camera.look_at, Vec3::new([0.0, 0.0, -1.0]));
+        assert_eq!(camera.vup, CameraDesc::default().vup);
+        assert_eq!(camera.vfov, 30.0);
+        assert_eq!(camera.aperture, CameraDesc::default().aperture);
+        assert!(camera.focus.is_none());
+
+        let mut settings = RenderSettings::default();
+        file.apply_settings(&mut settings).unwrap();
+        assert_eq!(settings.samples_per_pixel, 7);
+        assert!(file.files.is_empty());
+    }
+}
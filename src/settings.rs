@@ -0,0 +1,372 @@
+//
+// render settings
+//
+// all the knobs that used to be consts at the top of main.rs
+//
+
+use crate::utils::{ReflectionType, Color, Vec3};
+use crate::film::Filter;
+use crate::aov::Aov;
+use crate::io::{ExrPixel, ImageFormat};
+use crate::tonemap::ToneMap;
+use crate::sampler::SamplerKind;
+use crate::denoise;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// which scene to render
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneKind {
+    Main,         // scene::build_scene
+    RTiOWFinal,   // scene::build_rtiow_final_scene
+    File(String), // a scene description file (see scenefile.rs)
+}
+
+impl FromStr for SceneKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "main" => Ok(SceneKind::Main),
+            "final" => Ok(SceneKind::RTiOWFinal),
+            "" => Err("empty scene name".to_string()),
+            path => Ok(SceneKind::File(path.to_string())),
+        }
+    }
+}
+
+// what rays that miss everything see
+#[derive(Debug, Clone, Copy)]
+pub enum Background {
+    Sky,                    // Shirley's white-to-blue gradient
+    Solid(Color),           // e.g., black for scenes lit only by their lights
+    Gradient(Color, Color), // straight down to straight up
+}
+
+impl Background {
+    pub fn color(&self, dir: Vec3) -> Color {
+        let (bottom, top) = match *self {
+            Background::Sky => (Color::white(), Color::new([0.5, 0.7, 1.0])),
+            Background::Solid(c) => return c,
+            Background::Gradient(bottom, top) => (bottom, top),
+        };
+        let t = 0.5*(dir.normalize().y() + 1.0); // vertical percent along viewport
+        bottom*(1.0 - t) + top*t
+    }
+}
+
+// sky | black | white | r,g,b | r,g,b:r,g,b (bottom:top)
+impl FromStr for Background {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let color = |c: &str| -> Result<Color, String> {
+            let rgb: Vec<f32> = c.split(',').map(|n| n.trim().parse::<f32>()).collect::<Result<_, _>>()
+                .map_err(|_| format!("invalid background color: {}", c))?;
+            if rgb.len() != 3 || rgb.iter().any(|n| !(n.is_finite() && *n >= 0.0)) {
+                return Err(format!("background colors are r,g,b (non-negative), not {}", c));
+            }
+            Ok(Color::new([rgb[0], rgb[1], rgb[2]]))
+        };
+        match s {
+            "sky" => Ok(Background::Sky),
+            "black" => Ok(Background::Solid(Color::black())),
+            "white" => Ok(Background::Solid(Color::white())),
+            _ => match s.split_once(':') {
+                Some((bottom, top)) => Ok(Background::Gradient(color(bottom)?, color(top)?)),
+                None => Ok(Background::Solid(color(s)?)),
+            },
+        }
+    }
+}
+
+// when to save the image so far, while rendering progressively
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Snapshot {
+    Never,
+    Seconds(f32), // at the end of the first pass at least this long after the last one
+    Passes(u32),  // every so many passes
+}
+
+// never | <n>s | <n> (passes)
+impl FromStr for Snapshot {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid snapshot interval: {} (try 30s for seconds, or 2 for passes)", s);
+        match s.strip_suffix('s') {
+            _ if s == "never" => Ok(Snapshot::Never),
+            Some(secs) => match secs.parse::<f32>() {
+                Ok(secs) if secs > 0.0 && secs.is_finite() => Ok(Snapshot::Seconds(secs)),
+                _ => Err(invalid()),
+            },
+            None => match s.parse::<u32>() {
+                Ok(0) => Ok(Snapshot::Never),
+                Ok(passes) => Ok(Snapshot::Passes(passes)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    pub debug: bool,
+
+    // screen
+    pub image_width: u32,
+    pub image_height: u32,
+    pub aspect: f32, // width/height
+
+    // render
+    pub samples_per_pixel: u32,
+    pub max_depth: i32,
+    pub refl_type: ReflectionType, // Lambertian reflection equation
+    pub background: Background,
+
+    // camera
+    pub sampler: SamplerKind, // where in the pixel (and on the lens) samples go
+    pub filter: Filter,       // how samples are weighted into the pixels around them
+
+    // adaptive sampling: after its spp, a pixel keeps getting another spp at a time until
+    // its relative error is under adaptive_error or it's had max_spp (0 turns it off)
+    pub adaptive_error: f32,
+    pub max_spp: u32,            // 0 means 8x spp
+    pub heatmap: Option<String>, // where to save how many samples each pixel got
+
+    // progressive rendering (when any of these is set; see render.rs)
+    pub snapshot: Snapshot,
+    pub time_limit: f32,            // in seconds, 0 for none
+    pub checkpoint: Option<String>, // where to save the render's state after each pass...
+    pub resume: Option<String>,     // ...and where to pick it up from (see checkpoint.rs)
+
+    // extra passes saved with the image (see aov.rs)
+    pub aovs: Vec<Aov>,
+
+    // denoising (see denoise.rs): how many passes (0 for none), and where to also save the
+    // image as rendered
+    pub denoise: u32,
+    pub noisy: Option<String>,
+
+    // parallelism
+    pub threads: usize, // 0 means one per core
+    pub seed: u64,      // the same seed (and settings) renders the same image, bit for bit
+    pub tile_size: u32, // pixels per side of the square tiles handed to each thread
+
+    // scene and output
+    pub scene: SceneKind,
+    pub scene_files: Vec<PathBuf>, // what a scene file read (see scenefile::SceneFile), for checkpoints
+    pub output: String,
+    pub exr: ExrPixel,  // when output is a .exr
+    // (and when it's a .png)
+    pub tonemap: ToneMap,
+    pub exposure: f32,  // in EV, applied before tone mapping
+    pub bit_depth: u8,  // 8 or 16
+}
+
+impl RenderSettings {
+    // the old DEBUG/LITE/BOOK/FINAL combos, now chosen at runtime
+    pub fn preset(debug: bool, lite: bool, book: bool, fin: bool) -> Self {
+        let aspect: f32 = if fin { 3.0/2.0 } else { 16.0/9.0 };
+        let image_width: u32 = if fin && book { 1200 } else if book { 400 } else { 200 };
+        Self {
+            debug,
+            image_width,
+            image_height: (image_width as f32 / aspect) as u32,
+            aspect,
+            samples_per_pixel: if debug {1} else if lite {5} else if fin && book {500} else if book {100} else {26},
+            max_depth: if debug {4} else if lite {100} else if fin && book { 50 } else if book { 100 } else { 25 },
+            refl_type: ReflectionType::NormalPlusPointOnSphere,
+            background: Background::Sky,
+            sampler: SamplerKind::Sobol,
+            filter: Filter::Box { radius: 0.5 }, // (just the pixel's own samples, averaged)
+            adaptive_error: 0.0,
+            max_spp: 0,
+            heatmap: None,
+            snapshot: Snapshot::Never,
+            time_limit: 0.0,
+            checkpoint: None,
+            resume: None,
+            aovs: Vec::new(),
+            denoise: 0,
+            noisy: None,
+            threads: if debug { 1 } else { 0 }, // one thread keeps the debug output readable
+            seed: 0,
+            tile_size: 16,
+            scene: if fin { SceneKind::RTiOWFinal } else { SceneKind::Main },
+            scene_files: Vec::new(),
+            output: String::from(r"/tmp/smoothcanvas.png"),
+            exr: ExrPixel::Half,
+            tonemap: ToneMap::Clamp,
+            exposure: 0.0,
+            bit_depth: 8,
+        }
+    }
+
+    // keep aspect and height in agreement when one of the screen dims changes
+    pub fn set_width(&mut self, width: u32) {
+        self.image_width = width;
+        self.image_height = (width as f32 / self.aspect) as u32;
+    }
+
+    pub fn set_height(&mut self, height: u32) {
+        self.image_height = height;
+        self.aspect = self.image_width as f32 / height as f32;
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+        self.image_height = (self.image_width as f32 / aspect) as u32;
+    }
+
+    // names of the knobs `set` knows how to turn
+    pub const KEYS: [&'static str; 28] =
+        ["width", "height", "aspect", "spp", "max-depth", "refl", "background", "sampler", "filter",
+         "adaptive", "max-spp", "heatmap", "snapshot", "time-limit", "checkpoint", "resume", "threads",
+         "tile-size", "seed", "scene", "output", "exr", "tonemap", "exposure", "bit-depth", "aovs",
+         "denoise", "noisy"];
+
+    // set a knob by name (shared by the command line and scene files)
+    pub fn set(&mut self, key: &str, val: &str) -> Result<(), String> {
+        match key {
+            "width" => self.set_width(parse_num(key, val)?),
+            "height" => self.set_height(parse_num(key, val)?),
+            "aspect" => self.set_aspect(parse_aspect(val)?),
+            "spp" => self.samples_per_pixel = parse_num(key, val)?,
+            "max-depth" => self.max_depth = parse_num(key, val)?,
+            "refl" => self.refl_type = val.parse()?,
+            "background" => self.background = val.parse()?,
+            "sampler" => self.sampler = val.parse()?,
+            "filter" => self.filter = val.parse()?,
+            "adaptive" => self.adaptive_error = parse_num(key, val)?,
+            "max-spp" => self.max_spp = parse_num(key, val)?,
+            "heatmap" => self.heatmap = Some(val.to_string()),
+            "snapshot" => self.snapshot = val.parse()?,
+            "time-limit" => self.time_limit = parse_num(key, val)?,
+            "checkpoint" => self.checkpoint = Some(val.to_string()),
+            "resume" => self.resume = Some(val.to_string()),
+            "threads" => self.threads = parse_num(key, val)?,
+            "tile-size" => self.tile_size = parse_num(key, val)?,
+            "seed" => self.seed = parse_num(key, val)?,
+            "scene" => self.scene = val.parse()?,
+            "output" => self.output = val.to_string(),
+            "exr" => self.exr = val.parse()?,
+            "tonemap" => self.tonemap = val.parse()?,
+            "exposure" => self.exposure = parse_num(key, val)?,
+            "bit-depth" => self.bit_depth = parse_num(key, val)?,
+            "aovs" => self.aovs = Aov::parse_list(val)?,
+            "denoise" => self.denoise = parse_num(key, val)?,
+            "noisy" => self.noisy = Some(val.to_string()),
+            _ => return Err(format!("unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.image_width < 2 || self.image_height < 2 {
+            return Err(format!("image too small: {}x{}", self.image_width, self.image_height));
+        }
+        if self.samples_per_pixel == 0 {
+            return Err("need at least one sample per pixel".to_string());
+        }
+        if self.tile_size == 0 {
+            return Err("tiles need at least one pixel".to_string());
+        }
+        ImageFormat::from_path(&self.output, self.exr)?; // (better now than after the render)
+        if self.bit_depth != 8 && self.bit_depth != 16 {
+            return Err(format!("png bit depth must be 8 or 16, not {}", self.bit_depth));
+        }
+        if !self.exposure.is_finite() {
+            return Err(format!("invalid exposure: {}", self.exposure));
+        }
+        if !(self.adaptive_error >= 0.0 && self.adaptive_error.is_finite()) {
+            return Err(format!("invalid adaptive error target: {}", self.adaptive_error));
+        }
+        if self.max_spp != 0 && self.max_spp < self.samples_per_pixel {
+            return Err(format!("max-spp ({}) is less than spp ({})", self.max_spp, self.samples_per_pixel));
+        }
+        if let Some(heatmap) = &self.heatmap {
+            ImageFormat::from_path(heatmap, self.exr)?;
+        }
+        if !(self.time_limit >= 0.0 && self.time_limit.is_finite()) {
+            return Err(format!("invalid time limit: {}", self.time_limit));
+        }
+        if self.denoise > denoise::MAX_ITERATIONS {
+            return Err(format!("denoise takes at most {} passes, not {}", denoise::MAX_ITERATIONS, self.denoise));
+        }
+        if let Some(noisy) = &self.noisy {
+            if self.denoise == 0 {
+                return Err("nothing to save as noisy without denoise".to_string());
+            }
+            ImageFormat::from_path(noisy, self.exr)?;
+        }
+        Ok(())
+    }
+
+    // render in passes (see render.rs)
+    pub fn progressive(&self) -> bool {
+        self.snapshot != Snapshot::Never || self.time_limit > 0.0 || self.checkpoint.is_some() || self.resume.is_some()
+    }
+
+    // checkpoints go where asked, or back where the render was resumed from
+    pub fn checkpoint_path(&self) -> Option<&str> {
+        self.checkpoint.as_deref().or(self.resume.as_deref())
+    }
+
+    pub fn time_limit(&self) -> Option<std::time::Duration> {
+        if self.time_limit > 0.0 { Some(std::time::Duration::from_secs_f32(self.time_limit)) } else { None }
+    }
+
+    pub fn adaptive(&self) -> bool {
+        self.adaptive_error > 0.0
+    }
+
+    // the most samples any one pixel gets
+    pub fn max_samples(&self) -> u32 {
+        match (self.adaptive(), self.max_spp) {
+            (false, _) => self.samples_per_pixel,
+            (true, 0) => 8 * self.samples_per_pixel,
+            (true, max) => max,
+        }
+    }
+
+    // the AOVs to render: the ones asked for, plus what the denoiser goes by
+    pub fn rendered_aovs(&self) -> Vec<Aov> {
+        let mut aovs = self.aovs.clone();
+        if self.denoise > 0 {
+            for aov in [Aov::Normal, Aov::Albedo, Aov::Depth] {
+                if !aovs.contains(&aov) {
+                    aovs.push(aov);
+                }
+            }
+        }
+        aovs
+    }
+
+    // actual number of render threads to use
+    pub fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
+}
+
+fn parse_num<T: FromStr>(key: &str, val: &str) -> Result<T, String> {
+    val.parse().map_err(|_| format!("invalid value for {}: {}", key, val))
+}
+
+// accepts 1.5 or 3/2
+fn parse_aspect(val: &str) -> Result<f32, String> {
+    let aspect = match val.split_once('/') {
+        Some((w, h)) => parse_num::<f32>("aspect", w)? / parse_num::<f32>("aspect", h)?,
+        None => parse_num("aspect", val)?,
+    };
+    if !(aspect > 0.0 && aspect.is_finite()) {
+        return Err(format!("invalid aspect: {}", val));
+    }
+    Ok(aspect)
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self::preset(false, false, false, false)
+    }
+}
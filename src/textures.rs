@@ -0,0 +1,243 @@
+//
+// textures: colors that vary over a surface (or through space)
+//
+// Materials look their colors up here with the hit's uv and point, so anything that
+// used to be a Color can now be a checkerboard, a picture or a slab of marble.
+//
+
+use crate::*;
+use crate::tonemap::decode_srgb;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+
+pub trait Texture: Send + Sync {
+    fn value(&self, uv: Vec2, point: Vec3) -> Color;
+    fn log(&self) -> String;
+}
+
+impl fmt::Debug for dyn Texture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.log())
+    }
+}
+
+// the good old constant color
+pub struct SolidColor {
+    pub color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, uv: Vec2, point: Vec3) -> Color {
+        self.color
+    }
+
+    fn log(&self) -> String {
+        format!("▦ solid {}", self.color)
+    }
+}
+
+// 3d checkerboard of cubes `size` on a side (so it doesn't care about uvs)
+pub struct Checker {
+    pub size: f32,
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+}
+
+impl Checker {
+    pub fn new(size: f32, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self { size, even, odd }
+    }
+
+    pub fn colors(size: f32, even: Color, odd: Color) -> Self {
+        Self::new(size, Arc::new(SolidColor::new(even)), Arc::new(SolidColor::new(odd)))
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, uv: Vec2, point: Vec3) -> Color {
+        let cell = |x: f32| (x / self.size).floor() as i64;
+        if (cell(point.x()) + cell(point.y()) + cell(point.z())) % 2 == 0 {
+            self.even.value(uv, point)
+        } else {
+            self.odd.value(uv, point)
+        }
+    }
+
+    fn log(&self) -> String {
+        format!("▦ checker {} ({:?} / {:?})", self.size, self.even, self.odd)
+    }
+}
+
+// a picture wrapped on by uv (which repeats outside of [0,1])
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    data: Vec<Color>, // linear, top row first
+    name: String,
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, data: Vec<Color>) -> Self {
+        assert!(width > 0 && height > 0 && data.len() == width*height, "texture needs width*height colors");
+        Self { width, height, data, name: String::from("anon") }
+    }
+
+    // any 8 or 16 bit png (gray, rgb, with or without alpha, palettes too)
+    pub fn load_png(path: &str) -> Result<Self, String> {
+        let err = |e: &dyn fmt::Display| format!("can't read texture {}: {}", path, e);
+        let file = std::fs::File::open(path).map_err(|e| err(&e))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::EXPAND); // palettes and low bit depths to 8 bits
+        let mut reader = decoder.read_info().map_err(|e| err(&e))?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(|e| err(&e))?;
+        let channels = info.color_type.samples();
+        let (width, height) = (info.width as usize, info.height as usize);
+
+        let sample = |i: usize| -> f32 {
+            match info.bit_depth {
+                png::BitDepth::Sixteen => u16::from_be_bytes([buf[2*i], buf[2*i + 1]]) as f32 / 65535.0,
+                _ => buf[i] as f32 / 255.0,
+            }
+        };
+        let data = (0..width*height).map(|px| {
+            let rgb = match channels {
+                1 | 2 => [sample(px*channels); 3],
+                _ => [sample(px*channels), sample(px*channels + 1), sample(px*channels + 2)],
+            };
+            // pngs are sRGB encoded (our renders included), the renderer wants linear
+            Color::new(rgb.map(decode_srgb))
+        }).collect();
+
+        let mut tex = Self::new(width, height, data);
+        tex.name = path.to_string();
+        Ok(tex)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, uv: Vec2, point: Vec3) -> Color {
+        // nearest texel; v goes up, rows go down
+        let u = uv[0] - uv[0].floor();
+        let v = 1.0 - (uv[1] - uv[1].floor());
+        let i = ((u * self.width as f32) as usize).min(self.width - 1);
+        let j = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.data[j*self.width + i]
+    }
+
+    fn log(&self) -> String {
+        format!("▦ image {} ({}x{})", self.name, self.width, self.height)
+    }
+}
+
+// Ken Perlin's gradient noise (smooth, random-looking, and the same every time for a given seed)
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    const POINT_COUNT: usize = 256;
+
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..Self::POINT_COUNT).map(|_| loop {
+            let v = Vec3::new([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)]);
+            if v.len_squared() > 1e-4 && v.len_squared() <= 1.0 {
+                break v.normalize();
+            }
+        }).collect();
+        let mut perm = || {
+            let mut p: Vec<usize> = (0..Self::POINT_COUNT).collect();
+            p.shuffle(&mut rng);
+            p
+        };
+        let perm = [perm(), perm(), perm()];
+        Self { gradients, perm }
+    }
+
+    // in [-1,1], more or less
+    pub fn noise(&self, p: Vec3) -> f32 {
+        let f = [p.x().floor(), p.y().floor(), p.z().floor()];
+        let (u, v, w) = (p.x() - f[0], p.y() - f[1], p.z() - f[2]);
+        let cell = f.map(|x| x as i64);
+        let mask = Self::POINT_COUNT as i64 - 1;
+
+        // trilinear interpolation of the corners' gradients, Hermite smoothed
+        let (uu, vv, ww) = (u*u*(3.0 - 2.0*u), v*v*(3.0 - 2.0*v), w*w*(3.0 - 2.0*w));
+        let mut sum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let g = self.gradients[self.perm[0][((cell[0] + di) & mask) as usize]
+                                           ^ self.perm[1][((cell[1] + dj) & mask) as usize]
+                                           ^ self.perm[2][((cell[2] + dk) & mask) as usize]];
+                    let (fi, fj, fk) = (di as f32, dj as f32, dk as f32);
+                    let weight = Vec3::new([u - fi, v - fj, w - fk]);
+                    sum += (fi*uu + (1.0 - fi)*(1.0 - uu))
+                         * (fj*vv + (1.0 - fj)*(1.0 - vv))
+                         * (fk*ww + (1.0 - fk)*(1.0 - ww))
+                         * g.dot(weight);
+                }
+            }
+        }
+        sum
+    }
+
+    // a sum of ever smaller, fainter octaves of noise
+    pub fn turbulence(&self, p: Vec3, depth: usize) -> f32 {
+        let (mut sum, mut p, mut weight) = (0.0, p, 1.0);
+        for _ in 0..depth {
+            sum += weight * self.noise(p);
+            weight *= 0.5;
+            p = 2.0 * p;
+        }
+        sum.abs()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoiseKind {
+    Smooth,     // plain perlin noise
+    Turbulence, // camouflage-y
+    Marble,     // stripes along z, wiggled by turbulence
+}
+
+pub struct NoiseTexture {
+    pub kind: NoiseKind,
+    pub scale: f32,
+    pub color: Color,
+    noise: Perlin,
+}
+
+impl NoiseTexture {
+    const TURBULENCE_DEPTH: usize = 7;
+
+    pub fn new(kind: NoiseKind, scale: f32, color: Color) -> Self {
+        Self { kind, scale, color, noise: Perlin::new(0x5eed) }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, uv: Vec2, point: Vec3) -> Color {
+        let p = self.scale * point;
+        let k = match self.kind {
+            NoiseKind::Smooth => 0.5 * (1.0 + self.noise.noise(p)),
+            NoiseKind::Turbulence => self.noise.turbulence(p, Self::TURBULENCE_DEPTH),
+            NoiseKind::Marble => 0.5 * (1.0 + (p.z() + 10.0*self.noise.turbulence(p, Self::TURBULENCE_DEPTH)).sin()),
+        };
+        (k.clamp(0.0, 1.0) * self.color).opaque()
+    }
+
+    fn log(&self) -> String {
+        format!("▦ {:?} noise {} {}", self.kind, self.scale, self.color)
+    }
+}
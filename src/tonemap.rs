@@ -0,0 +1,71 @@
+//
+// tone mapping: from the renderer's linear, unbounded light to what a screen can show
+//
+// Only the PNG writer uses this; the float formats (see io.rs) save the light as it was
+// rendered and leave it to whoever opens them.
+//
+
+use crate::*;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    Clamp,                           // anything over 1 is just white (the old behavior)
+    Reinhard,                        // L/(1+L), on luminance so hues hold; never quite reaches white
+    ExtendedReinhard { white: f32 }, // the same, but luminance `white` (and up) maps to white
+    Aces,                            // Narkowicz's fit of the ACES filmic curve (contrasty, per channel)
+}
+
+impl FromStr for ToneMap {
+    type Err = String;
+    // clamp | reinhard | reinhard:<white> | aces
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("reinhard", white)) => match white.parse::<f32>() {
+                Ok(white) if white > 0.0 && white.is_finite() => Ok(ToneMap::ExtendedReinhard { white }),
+                _ => Err(format!("invalid reinhard white point: {}", white)),
+            },
+            Some(_) => Err(format!("invalid tone map: {} (try clamp, reinhard, reinhard:<white> or aces)", s)),
+            None => match s {
+                "clamp" => Ok(ToneMap::Clamp),
+                "reinhard" => Ok(ToneMap::Reinhard),
+                "aces" => Ok(ToneMap::Aces),
+                _ => Err(format!("invalid tone map: {} (try clamp, reinhard, reinhard:<white> or aces)", s)),
+            },
+        }
+    }
+}
+
+impl ToneMap {
+    // squeeze a linear color into [0,1] (still linear, encode_srgb comes after)
+    pub fn apply(&self, c: Color) -> Color {
+        let lum = 0.2126*c[0] + 0.7152*c[1] + 0.0722*c[2];
+        let scaled = |l: f32| if lum > 0.0 { (l / lum) * c } else { Color::black() };
+        let mapped = match *self {
+            ToneMap::Clamp => c,
+            ToneMap::Reinhard => scaled(lum / (1.0 + lum)),
+            ToneMap::ExtendedReinhard { white } => scaled(lum * (1.0 + lum/(white*white)) / (1.0 + lum)),
+            ToneMap::Aces => {
+                let aces = |x: f32| (x*(2.51*x + 0.03)) / (x*(2.43*x + 0.59) + 0.14);
+                Color::new([aces(c[0]), aces(c[1]), aces(c[2])])
+            },
+        };
+        let clamp = |x: f32| if x > 0.0 { x.min(1.0) } else { 0.0 }; // (nan too)
+        Color::new([clamp(mapped[0]), clamp(mapped[1]), clamp(mapped[2])])
+    }
+}
+
+// exposure in EV (stops): each one doubles the light
+pub fn exposure_scale(ev: f32) -> f32 {
+    2f32.powf(ev)
+}
+
+// the sRGB transfer function (IEC 61966-2-1): linear [0,1] to what's stored in the file
+pub fn encode_srgb(x: f32) -> f32 {
+    if x <= 0.0031308 { 12.92 * x } else { 1.055 * x.powf(1.0/2.4) - 0.055 }
+}
+
+// and back (for reading images in)
+pub fn decode_srgb(x: f32) -> f32 {
+    if x <= 0.04045 { x / 12.92 } else { ((x + 0.055) / 1.055).powf(2.4) }
+}
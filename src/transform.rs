@@ -0,0 +1,306 @@
+//
+// transforms: placing things without working out u, v and w by hand
+//
+// Transform is a chainable builder for where a Jumble's contents go in the world, each
+// step applied after the ones before it (so scale, then turn, then move, reads the way it
+// happens):
+//
+//   let csys = Transform::new()
+//       .scale(Vec3::new([0.5, 1.0, 1.0]))
+//       .rotate_deg(45.0, Vec3::new([0.0, 1.0, 0.0]))
+//       .translate(Vec3::new([1.0, 0.5, -1.0]))
+//       .build()?; // (unless something squashed it flat, see MatrixError)
+//   jumble.set_csys(csys);
+//
+// Quaternions are for orientations that get combined or blended (slerp keeps the turn
+// steady where lerping matrices would squash things halfway through).
+//
+// And a Decomposition goes the other way, from a matrix (or a CoordSys) back to the scale,
+// skew, rotation and translation that would build it.
+//
+
+use crate::*;
+use std::ops::Mul;
+
+// a rotation as w + xi + yj + zk (unit length, at least after normalize)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub v: Vec3, // x, y, z
+}
+
+impl Quaternion {
+    pub const fn identity() -> Self {
+        Self { w: 1.0, v: Vec3::zero() }
+    }
+
+    // rad around axis (counterclockwise looking down it, like Matrix::rotation_about)
+    pub fn from_axis_angle(axis: Vec3, rad: f32) -> Self {
+        if axis.near_zero() {
+            return Self::identity();
+        }
+        let (sin, cos) = (0.5 * rad).sin_cos();
+        Self { w: cos, v: sin * axis.normalize() }
+    }
+
+    // the smallest rotation that turns direction from into direction to
+    pub fn between(from: Vec3, to: Vec3) -> Self {
+        if from.near_zero() || to.near_zero() {
+            return Self::identity();
+        }
+        let (from, to) = (from.normalize(), to.normalize());
+        let cos = from.dot(to);
+        if cos < -1.0 + 1e-6 {
+            // opposite: half a turn around anything perpendicular
+            let (perp, _) = orthonormal_basis(from);
+            return Self { w: 0.0, v: perp };
+        }
+        // (half way between from and to is the half angle a quaternion wants)
+        Self { w: 1.0 + cos, v: from.cross(to) }.normalize()
+    }
+
+    // the rotation matrix m (which had better be one) as a quaternion, by Shepperd's method:
+    // start from whichever of w, x, y and z is biggest, so nothing's divided by ~0
+    pub fn from_matrix(m: &Matrix) -> Self {
+        let r = |i: usize, j: usize| m.rows[i][j];
+        let trace = r(0, 0) + r(1, 1) + r(2, 2);
+        let q = if trace > 0.0 {
+            let s = 2.0 * (1.0 + trace).sqrt(); // 4w
+            Self { w: 0.25 * s, v: Vec3::new([(r(2, 1) - r(1, 2)) / s, (r(0, 2) - r(2, 0)) / s, (r(1, 0) - r(0, 1)) / s]) }
+        } else if r(0, 0) > r(1, 1) && r(0, 0) > r(2, 2) {
+            let s = 2.0 * (1.0 + r(0, 0) - r(1, 1) - r(2, 2)).sqrt(); // 4x
+            Self { w: (r(2, 1) - r(1, 2)) / s, v: Vec3::new([0.25 * s, (r(0, 1) + r(1, 0)) / s, (r(0, 2) + r(2, 0)) / s]) }
+        } else if r(1, 1) > r(2, 2) {
+            let s = 2.0 * (1.0 + r(1, 1) - r(0, 0) - r(2, 2)).sqrt(); // 4y
+            Self { w: (r(0, 2) - r(2, 0)) / s, v: Vec3::new([(r(0, 1) + r(1, 0)) / s, 0.25 * s, (r(1, 2) + r(2, 1)) / s]) }
+        } else {
+            let s = 2.0 * (1.0 + r(2, 2) - r(0, 0) - r(1, 1)).sqrt(); // 4z
+            Self { w: (r(1, 0) - r(0, 1)) / s, v: Vec3::new([(r(0, 2) + r(2, 0)) / s, (r(1, 2) + r(2, 1)) / s, 0.25 * s]) }
+        };
+        q.normalize()
+    }
+
+    // axis and angle (in radians) back out
+    pub fn axis_angle(&self) -> (Vec3, f32) {
+        let q = self.normalize();
+        let sin = q.v.len();
+        if sin < 1e-6 {
+            return (Vec3::new([1.0, 0.0, 0.0]), 0.0);
+        }
+        (q.v / sin, 2.0 * sin.atan2(q.w))
+    }
+
+    pub fn dot(&self, other: Quaternion) -> f32 {
+        self.w * other.w + self.v.dot(other.v)
+    }
+
+    pub fn len(&self) -> f32 {
+        self.dot(*self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        let len = self.len();
+        if len == 0.0 {
+            return Self::identity();
+        }
+        Self { w: self.w / len, v: self.v / len }
+    }
+
+    // the opposite turn (for unit quaternions)
+    pub fn conjugate(&self) -> Self {
+        Self { w: self.w, v: -self.v }
+    }
+
+    pub fn rotate(&self, vec: Vec3) -> Vec3 {
+        // q v q*, folded down: v + 2w(u x v) + 2u x (u x v)
+        let t = 2.0 * self.v.cross(vec);
+        vec + self.w * t + self.v.cross(t)
+    }
+
+    // t of the way from self to other at a steady speed, the short way around
+    pub fn slerp(&self, other: Quaternion, t: f32) -> Self {
+        let (a, mut b) = (self.normalize(), other.normalize());
+        let mut cos = a.dot(b);
+        if cos < 0.0 {
+            // q and -q are the same rotation; going to the nearer one is the short way
+            b = Self { w: -b.w, v: -b.v };
+            cos = -cos;
+        }
+        let (wa, wb) = if cos > 0.9995 {
+            (1.0 - t, t) // (so close a plain lerp is just as good, and sin(angle) ~ 0 isn't)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Self { w: wa * a.w + wb * b.w, v: wa * a.v + wb * b.v }.normalize()
+    }
+
+    // the same rotation as a matrix (moving points, like Matrix::rotation_about)
+    pub fn to_matrix(&self) -> Matrix {
+        let q = self.normalize();
+        let (w, x, y, z) = (q.w, q.v.x(), q.v.y(), q.v.z());
+        Matrix::new([1.0 - 2.0*(y*y + z*z), 2.0*(x*y - w*z),       2.0*(x*z + w*y),       0.0],
+                    [2.0*(x*y + w*z),       1.0 - 2.0*(x*x + z*z), 2.0*(y*z - w*x),       0.0],
+                    [2.0*(x*z - w*y),       2.0*(y*z + w*x),       1.0 - 2.0*(x*x + y*y), 0.0],
+                    [0.0,                   0.0,                   0.0,                   1.0])
+    }
+}
+
+// self after other (like matrices: (a * b).rotate(v) is a.rotate(b.rotate(v)))
+impl Mul for Quaternion {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Self { w: self.w * other.w - self.v.dot(other.v),
+               v: self.w * other.v + other.w * self.v + self.v.cross(other.v) }
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+// where things go: from a Jumble's own coordinates to the world's, built up a step at a time
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub matrix: Matrix, // local to world
+}
+
+impl Transform {
+    // stays put
+    pub const fn new() -> Self {
+        Self { matrix: Matrix::identity() }
+    }
+
+    // then whatever m does
+    pub fn then(self, m: Matrix) -> Self {
+        Self { matrix: m * self.matrix }
+    }
+
+    pub fn scale(self, s: Vec3) -> Self {
+        self.then(Matrix::scale(s))
+    }
+
+    pub fn uniform_scale(self, s: f32) -> Self {
+        self.scale(Vec3::new([s, s, s]))
+    }
+
+    // (see Matrix::shear and Matrix::skew)
+    pub fn shear(self, axis: Axis, by: Axis, amount: f32) -> Self {
+        self.then(Matrix::shear(axis, by, amount))
+    }
+
+    pub fn skew(self, axis: Axis, by: Axis, rad: f32) -> Self {
+        self.then(Matrix::skew(axis, by, rad))
+    }
+
+    // turns around axis, which goes through the origin (so usually before translate)
+    pub fn rotate(self, rad: f32, axis: Vec3) -> Self {
+        self.then(Matrix::rotation_about(rad, axis))
+    }
+
+    pub fn rotate_deg(self, deg: f32, axis: Vec3) -> Self {
+        self.rotate(deg.to_radians(), axis)
+    }
+
+    // turned so what pointed along from points along to
+    pub fn rotate_onto(self, from: Vec3, to: Vec3) -> Self {
+        self.then(Matrix::rotation_onto(from, to))
+    }
+
+    pub fn orient(self, q: Quaternion) -> Self {
+        self.then(q.to_matrix())
+    }
+
+    pub fn translate(self, t: Vec3) -> Self {
+        self.then(Matrix::translation(t))
+    }
+
+    // facing at (with -z, like the camera) from from, y toward up (see Matrix::look_at)
+    pub fn look_at(self, from: Vec3, at: Vec3, up: Vec3) -> Self {
+        self.then(Matrix::look_at(from, at, up))
+    }
+
+    // the CoordSys for Jumble::set_csys (an error if a zero scale or the like squashed it flat)
+    pub fn build(&self) -> Result<CoordSys, MatrixError> {
+        CoordSys::from_matrix(self.matrix)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// an affine matrix taken apart: it's translate * rotate * shear * scale, so the Transform
+//   scale(scale).then(shear).orient(rotation).translate(translation)
+// builds it again. shear is (x by y, x by z, y by z) as in Matrix::shear, all 0 unless the
+// axes weren't perpendicular, and a mirror image gets a negative x scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposition {
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub shear: Vec3,
+    pub scale: Vec3,
+}
+
+impl Decomposition {
+    // Gram-Schmidt on the columns (where local x, y and z end up): x's length is its scale,
+    // y loses the part along x (that's shear), and so on
+    pub fn of(m: &Matrix) -> Result<Self, MatrixError> {
+        m.inverse()?; // (the easiest way to be sure it's not flat)
+        if !m.is_affine() {
+            return Err(MatrixError::NotAffine);
+        }
+        let col = |i: usize| Vec3::new([m.rows[0][i], m.rows[1][i], m.rows[2][i]]);
+        let (c0, c1, c2) = (col(0), col(1), col(2));
+
+        let mut sx = c0.len();
+        let mut e0 = c0 / sx;
+        let mut xy = e0.dot(c1);
+        let c1 = c1 - xy * e0;
+        let sy = c1.len();
+        let e1 = c1 / sy;
+        let mut xz = e0.dot(c2);
+        let yz = e1.dot(c2);
+        let c2 = c2 - xz * e0 - yz * e1;
+        let sz = c2.len();
+        let e2 = c2 / sz;
+
+        if e0.cross(e1).dot(e2) < 0.0 {
+            // mirrored: make the rotation a rotation again by flipping x (and what's along it)
+            e0 = -e0;
+            sx = -sx;
+            xy = -xy;
+            xz = -xz;
+        }
+        let rotation = Matrix::new([e0.x(), e1.x(), e2.x(), 0.0],
+                                   [e0.y(), e1.y(), e2.y(), 0.0],
+                                   [e0.z(), e1.z(), e2.z(), 0.0],
+                                   [0.0,    0.0,    0.0,    1.0]);
+        Ok(Self { translation: col(3),
+                  rotation: Quaternion::from_matrix(&rotation),
+                  shear: Vec3::new([xy / sy, xz / sz, yz / sz]),
+                  scale: Vec3::new([sx, sy, sz]) })
+    }
+
+    // (not the three Matrix::shears multiplied, which would pick up a bit of each other)
+    pub fn shear_matrix(&self) -> Matrix {
+        let [xy, xz, yz] = self.shear.v;
+        Matrix::new([1.0, xy,  xz,  0.0],
+                    [0.0, 1.0, yz,  0.0],
+                    [0.0, 0.0, 1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0])
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::new()
+            .scale(self.scale)
+            .then(self.shear_matrix())
+            .orient(self.rotation)
+            .translate(self.translation)
+    }
+}
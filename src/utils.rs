@@ -35,49 +35,103 @@ pub fn deg_to_rad(deg: f32) -> f32 {
 
 use std::ops::{Mul, Div, Sub, Add, Neg, AddAssign, SubAssign, MulAssign, DivAssign, Index, IndexMut};
 use std::fmt;
-use crate::DEBUG;
 use std::cmp::{PartialEq};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::transform::Decomposition;
 
 // generate more evenly distributed random values
-use rand::{Rng, thread_rng};
+use rand::Rng;
 use rand::distributions::{Distribution, Uniform};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Axis { X, Y, Z }
 
-pub fn random_point_in_unit_sphere() -> Vec3 {
+impl Axis {
+    pub const fn index(&self) -> usize {
+        match self { Axis::X => 0, Axis::Y => 1, Axis::Z => 2 }
+    }
+}
+
+// FNV-1a: a quick hash that (unlike std's) is the same from one build to the next
+pub fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// runtime replacement for the old DEBUG const (set once from RenderSettings)
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+pub fn debug() -> bool {
+    DEBUG.load(Ordering::Relaxed)
+}
+
+pub fn set_debug(on: bool) {
+    DEBUG.store(on, Ordering::Relaxed);
+}
+
+// (the random bits all take the rng to use, so renders can be repeated, see render.rs)
+pub fn random_point_in_unit_sphere<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
     loop {
-        let v = Vec3::rand();
+        let v = Vec3::rand(rng);
         if v.len_squared() < 1.0 {
             return v;
         }
     }
 }
 
-pub fn random_point_in_unit_disc() -> Vec3 {
+pub fn random_point_in_unit_disc<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
     loop {
-        let v = Vec2::rand();
+        let v = Vec2::rand(rng);
         if v.len_squared() < 1.0 {
             return Vec3::new([v[0], v[1], 0.0]);
         }
     }
 }
 
-pub fn random_unit_vector() -> Vec3 {
-    random_point_in_unit_sphere().normalize()
+pub fn random_unit_vector<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
+    random_point_in_unit_sphere(rng).normalize()
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ReflectionType {
     NormalPlusPointInSphere,
     NormalPlusPointOnSphere,
     PointOnHemisphere,
 }
 
-pub fn random_direction(ref_type: ReflectionType, normal: Vec3) -> Vec3 {
+impl FromStr for ReflectionType {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "in-sphere" => Ok(ReflectionType::NormalPlusPointInSphere),
+            "on-sphere" => Ok(ReflectionType::NormalPlusPointOnSphere),
+            "hemisphere" => Ok(ReflectionType::PointOnHemisphere),
+            _ => Err(format!("unknown reflection type: {}", s)),
+        }
+    }
+}
+
+// two unit vectors that make a right-handed orthonormal basis with unit vector n
+// (Duff et al., "Building an Orthonormal Basis, Revisited", 2017)
+pub fn orthonormal_basis(n: Vec3) -> (Vec3, Vec3) {
+    let sign = 1.0f32.copysign(n.z());
+    let a = -1.0 / (sign + n.z());
+    let b = n.x() * n.y() * a;
+    (Vec3::new([1.0 + sign * n.x() * n.x() * a, sign * b, -sign * n.x()]),
+     Vec3::new([b, sign + n.y() * n.y() * a, -n.y()]))
+}
+
+pub fn random_direction<R: Rng + ?Sized>(ref_type: ReflectionType, normal: Vec3, rng: &mut R) -> Vec3 {
     match ref_type {
-        ReflectionType::NormalPlusPointInSphere => return normal + random_point_in_unit_sphere(),
-        ReflectionType::NormalPlusPointOnSphere => return normal + random_unit_vector(),
+        ReflectionType::NormalPlusPointInSphere => return normal + random_point_in_unit_sphere(rng),
+        ReflectionType::NormalPlusPointOnSphere => return normal + random_unit_vector(rng),
         ReflectionType::PointOnHemisphere => {
-            let vec = random_unit_vector();
+            let vec = random_unit_vector(rng);
             return if vec.dot(normal) > 0.0 { vec } else { -vec };
         },
     }
@@ -97,8 +151,7 @@ impl Color {
         Self(Vec4::new(v))
     }
 
-    pub fn rand() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn rand<R: Rng + ?Sized>(rng: &mut R) -> Self {
         Self(Vec4::new([rng.gen(), rng.gen(), rng.gen(), 1.0]))
     }
 
@@ -106,6 +159,20 @@ impl Color {
 
     pub const fn black() -> Self { Self(Vec4::new([0.0, 0.0, 0.0, 1.0])) }
     pub const fn white() -> Self { Self(Vec4::new([1.0, 1.0, 1.0, 1.0])) }
+
+    pub fn is_black(&self) -> bool {
+        self.0[0] <= 0.0 && self.0[1] <= 0.0 && self.0[2] <= 0.0
+    }
+
+    // perceived brightness (Rec. 709 weights, for linear rgb)
+    pub fn luminance(&self) -> f32 {
+        0.2126*self.0[0] + 0.7152*self.0[1] + 0.0722*self.0[2]
+    }
+
+    // the same color with alpha back at 1 (adding colors adds their alphas too)
+    pub fn opaque(&self) -> Self {
+        Self::new([self.0[0], self.0[1], self.0[2]])
+    }
 }
 
 impl fmt::Display for Color {
@@ -347,16 +414,14 @@ impl Vec3 {
     }
 
     // vector with values in range [0,1)
-    pub fn rand() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn rand<R: Rng + ?Sized>(rng: &mut R) -> Self {
         Self { v: [rng.gen_range(-1.0..=1.0),
                    rng.gen_range(-1.0..=1.0),
                    rng.gen_range(-1.0..=1.0)] }
     }
 
     // return vector of n purportedly well-distributed random Vec3s
-    pub fn rand_arr(n: u32) -> Vec<Self> {
-        let mut rng = thread_rng();
+    pub fn rand_arr<R: Rng + ?Sized>(n: u32, rng: &mut R) -> Vec<Self> {
         let unitx = Uniform::new(0.0, 1.0); // maybe more uniform than otherwise
         let unity = Uniform::new(0.0, 1.0); // maybe more uniform than otherwise
         let unitz = Uniform::new(0.0, 1.0); // maybe more uniform than otherwise
@@ -364,7 +429,7 @@ impl Vec3 {
         for _ in 0..n {
             // FIXME: what's the diff between these? Maybe rng distributions? Pick one.
             //ret.push(Self { v: [rng.sample(unitx), rng.sample(unity), rng.sample(unitz)] });
-            ret.push(Self { v: [unitx.sample(&mut rng), unity.sample(&mut rng), unitz.sample(&mut rng)] });
+            ret.push(Self { v: [unitx.sample(rng), unity.sample(rng), unitz.sample(rng)] });
         }
         ret
     }
@@ -427,12 +492,12 @@ impl Vec3 {
     // refraction_ratio is etai[ncident] / etat[ransmitted]
     pub fn refract(&self, n: Vec3, refraction_ratio: f32, cos_theta: f32) -> Vec3 {
         let vt_perp =  refraction_ratio * (*self + n*cos_theta);
-        if DEBUG {
+        if debug() {
             println!("\tvt_perp: {}", vt_perp);
         }
 
         let vt_par = -1.0*n * (1.0 - vt_perp.len_squared()).abs().sqrt();
-        if DEBUG {
+        if debug() {
             println!("\tvt_par: {}", vt_par);
             println!("\tvt_par.len(): {}", vt_par.len());
             println!("\tn.len(): {}", n.len());
@@ -522,6 +587,123 @@ impl Range {
     }
 }
 
+// an axis-aligned bounding box (empty when min > max)
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub struct AABoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl fmt::Display for AABoundingBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "▢ [{} .. {}]", self.min, self.max)
+    }
+}
+
+impl AABoundingBox {
+    pub const fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    // contains nothing, so unioning with it is a no-op
+    pub const fn empty() -> Self {
+        Self { min: Vec3::new([f32::INFINITY; 3]), max: Vec3::new([f32::NEG_INFINITY; 3]) }
+    }
+
+    // contains everything (e.g., for things that don't know their bounds)
+    pub const fn infinite() -> Self {
+        Self { min: Vec3::new([f32::NEG_INFINITY; 3]), max: Vec3::new([f32::INFINITY; 3]) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+    }
+
+    pub fn is_finite(&self) -> bool {
+        (0..3).all(|i| self.min[i].is_finite() && self.max[i].is_finite())
+    }
+
+    pub fn union(&self, other: &AABoundingBox) -> Self {
+        Self { min: Vec3::new([self.min.x().min(other.min.x()), self.min.y().min(other.min.y()), self.min.z().min(other.min.z())]),
+               max: Vec3::new([self.max.x().max(other.max.x()), self.max.y().max(other.max.y()), self.max.z().max(other.max.z())]) }
+    }
+
+    pub fn grow(&self, pt: Vec3) -> Self {
+        self.union(&AABoundingBox::new(pt, pt))
+    }
+
+    pub fn extent(&self) -> Vec3 {
+        if self.is_empty() { Vec3::zero() } else { self.max - self.min }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let e = self.extent();
+        2.0 * (e.x()*e.y() + e.y()*e.z() + e.z()*e.x())
+    }
+
+    // axis along which the box is longest (0: x, 1: y, 2: z)
+    pub fn longest_axis(&self) -> usize {
+        let e = self.extent();
+        if e.x() >= e.y() && e.x() >= e.z() { 0 } else if e.y() >= e.z() { 1 } else { 2 }
+    }
+
+    // bounds of this box after transforming it (by all eight of its corners)
+    pub fn transform(&self, m: &Matrix) -> Self {
+        if self.is_empty() || !self.is_finite() {
+            return *self;
+        }
+        let mut bbox = AABoundingBox::empty();
+        for i in 0..8 {
+            let corner = Vec3::new([if i & 1 == 0 { self.min.x() } else { self.max.x() },
+                                    if i & 2 == 0 { self.min.y() } else { self.max.y() },
+                                    if i & 4 == 0 { self.min.z() } else { self.max.z() }]);
+            bbox = bbox.grow(m.apply_to_point(corner));
+        }
+        bbox
+    }
+
+    // slab test: does the ray pass through the box somewhere in [tmin, tmax]?
+    // (inv_dir is 1/ray.dir, computed once per ray rather than once per box)
+    pub fn hit(&self, origin: Vec3, inv_dir: Vec3, mut tmin: f32, mut tmax: f32) -> bool {
+        for a in 0..3 {
+            let t0 = (self.min[a] - origin[a]) * inv_dir[a];
+            let t1 = (self.max[a] - origin[a]) * inv_dir[a];
+            let (t0, t1) = if inv_dir[a] < 0.0 { (t1, t0) } else { (t0, t1) };
+            tmin = tmin.max(t0); // NOTE: max/min ignore the NaNs from 0*inf
+            tmax = tmax.min(t1);
+            if tmax < tmin {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// what can go wrong turning a matrix around (or into a CoordSys)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatrixError {
+    NotFinite, // a nan or infinity in it
+    NotAffine, // the bottom row isn't 0 0 0 1 (a projection, which can't place things)
+    Singular,  // squashes space flat: a zero scale, or axes in a plane (or a line)
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatrixError::NotFinite => write!(f, "matrix has a nan or infinity in it"),
+            MatrixError::NotAffine => write!(f, "matrix isn't affine (its bottom row should be 0 0 0 1)"),
+            MatrixError::Singular => write!(f, "matrix is singular (a zero scale, or axes that don't span 3d)"),
+        }
+    }
+}
+
+impl std::error::Error for MatrixError {}
+
 #[derive(Debug)]
 #[derive(Copy, Clone)]
 pub struct CoordSys {
@@ -540,41 +722,36 @@ impl CoordSys {
         }
     }
 
-    pub fn from_matrix(m: Matrix) -> Self {
-        let m_in = m;
-        let m_out = m.generic_inverse();
-        let m_out_normal = Matrix::biorthogonal_basis(m_out.u(),m_out.v(),m_out.w());
-
-        Self { m_in,
-               m_out,
-               m_out_normal,
+    // from where m puts things (local to world, like Transform::matrix), which can be any
+    // affine matrix that doesn't squash space flat -- scaled, turned, skewed, moved, in any order
+    pub fn from_matrix(m: Matrix) -> Result<Self, MatrixError> {
+        if !m.is_affine() {
+            return Err(if m.is_finite() { MatrixError::NotAffine } else { MatrixError::NotFinite });
         }
-    }
-
-    pub fn new(origin: Vec3, s: Vec3, u: Vec3, v: Vec3, w: Vec3) -> Self {
-        // gets rays to the party (normals aren't invited)
-        let translate = Matrix::translation(-origin);
-        let rotate = Matrix::biorthogonal_basis(u,v,w);
-        let scale = Matrix::scale(1.0 / s);
-        let m_in = scale * rotate * translate;
-
-        // gets the normals outta here (TODO: be sure to normalize them after transforming)
-        let m_out_normal = m_in.transpose();
+        let m_in = m.inverse()?;
 
-        // gets points home safe (normals take a different bus)
-        let scale = Matrix::scale(s);
-        let rotate =  Matrix::basis(u, v, w).transpose();
-        let translate = Matrix::translation(origin);
-        let m_out = translate * rotate * scale;
+        // normals go out by the inverse transpose, i.e. m_in's transpose (whose bottom row
+        // picks up m_in's translation, but normals are vectors, so that's multiplied by 0)
+        Ok(Self { m_in, m_out: m, m_out_normal: m_in.transpose() })
+    }
 
-        // TODO: compare these and wonder why they're not the same :-O
-        let generic_inv = m_in.generic_inverse();
-        println!("m: {}", m_in);
-        println!("inverse: {}", m_out);
-        println!("generic_inverse: {}", generic_inv);
-        // TODO: ...then delete these three lines. Well, five with comments.
+    // origin, then the directions of the local axes (their lengths don't matter), each
+    // scaled by s (so u, v and w needn't be perpendicular, that's skew)
+    pub fn new(origin: Vec3, s: Vec3, u: Vec3, v: Vec3, w: Vec3) -> Result<Self, MatrixError> {
+        if u.near_zero() || v.near_zero() || w.near_zero() {
+            return Err(MatrixError::Singular);
+        }
+        let (u, v, w) = (u.normalize(), v.normalize(), w.normalize());
+        let axes = Matrix::new([u.x(), v.x(), w.x(), 0.0],
+                               [u.y(), v.y(), w.y(), 0.0],
+                               [u.z(), v.z(), w.z(), 0.0],
+                               [0.0,   0.0,   0.0,   1.0]);
+        Self::from_matrix(Matrix::translation(origin) * axes * Matrix::scale(s))
+    }
 
-        Self { m_in, m_out, m_out_normal }
+    // back to scale, skew, rotate and translate (see transform.rs)
+    pub fn decompose(&self) -> Decomposition {
+        Decomposition::of(&self.m_out).expect("a CoordSys's matrix is always invertible")
     }
 
     // NOTE: do the next six functions really need to be here? instead self.m_in(vec) seems fine
@@ -658,8 +835,101 @@ impl Matrix {
         Vec3::new([vec[0], vec[1], vec[2]])
     }
 
+    pub fn is_finite(&self) -> bool {
+        self.rows.iter().all(|r| r.v.iter().all(|x| x.is_finite()))
+    }
+
+    // bottom row 0 0 0 1 (everything but projections)
+    pub fn is_affine(&self) -> bool {
+        self.rows[3].v == [0.0, 0.0, 0.0, 1.0] && self.is_finite()
+    }
+
+    // the inverse, or why there isn't one
+    //
+    // Gauss-Jordan with partial pivoting, in f64 -- just the upper 3x3 for affine matrices
+    // (their translation undone afterwards, -A⁻¹t), so the bottom row stays exactly 0 0 0 1.
+    // Whether the matrix squashes space flat goes by its volume compared to its axes'
+    // lengths, which doesn't care how big or small things are, only how flat. And then
+    // the answer gets checked: M M⁻¹ has to come out as the identity.
+    pub fn inverse(&self) -> Result<Matrix, MatrixError> {
+        if !self.is_finite() {
+            return Err(MatrixError::NotFinite);
+        }
+        let affine = self.is_affine();
+        let n = if affine { 3 } else { 4 };
+        let mut a = [[0.0f64; 4]; 4];
+        let mut inv = [[0.0f64; 4]; 4];
+        for (i, row) in a.iter_mut().enumerate().take(n) {
+            for (j, x) in row.iter_mut().enumerate().take(n) {
+                *x = self.rows[i][j] as f64;
+            }
+            inv[i][i] = 1.0;
+        }
+
+        // flatness: |det| over the product of the columns' lengths (1 for perpendicular axes)
+        let col_lengths: f64 = (0..n).map(|j| a.iter().take(n).map(|row| row[j] * row[j]).sum::<f64>().sqrt()).product();
+        if col_lengths == 0.0 {
+            return Err(MatrixError::Singular);
+        }
+
+        let mut det = 1.0;
+        for col in 0..n {
+            let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs())).unwrap();
+            if a[pivot][col] == 0.0 {
+                return Err(MatrixError::Singular);
+            }
+            if pivot != col {
+                a.swap(pivot, col);
+                inv.swap(pivot, col);
+                det = -det;
+            }
+            let p = a[col][col];
+            det *= p;
+            for j in 0..n {
+                a[col][j] /= p;
+                inv[col][j] /= p;
+            }
+            for i in (0..n).filter(|&i| i != col) {
+                let k = a[i][col];
+                for j in 0..n {
+                    a[i][j] -= k * a[col][j];
+                    inv[i][j] -= k * inv[col][j];
+                }
+            }
+        }
+        if det.abs() / col_lengths < 1e-6 {
+            return Err(MatrixError::Singular);
+        }
+
+        let mut m = Matrix::identity();
+        for (row, inv_row) in m.rows.iter_mut().zip(inv.iter()).take(n) {
+            for (x, inv_x) in row.v.iter_mut().zip(inv_row.iter()).take(n) {
+                *x = *inv_x as f32;
+            }
+            if affine {
+                row.v[3] = -(0..3).map(|j| inv_row[j] * self.rows[j][3] as f64).sum::<f64>() as f32;
+            }
+        }
+
+        // (anything this far off is too close to flat for f32 to be any use anyway). Only the
+        // block that was inverted gets checked -- an affine matrix's translation was worked out
+        // exactly -- and each entry against the size of what went into it, so big numbers
+        // (things far from the origin) don't count as error.
+        let check = *self * m;
+        let bad = (0..n).flat_map(|i| (0..n).map(move |j| (i, j))).any(|(i, j)| {
+            let size: f32 = (0..n).map(|k| (self.rows[i][k] * m.rows[k][j]).abs()).sum();
+            (check.rows[i][j] - if i == j { 1.0 } else { 0.0 }).abs() > 1e-3 * size.max(1.0)
+        });
+        if bad || !m.is_finite() {
+            return Err(MatrixError::Singular);
+        }
+        Ok(m)
+    }
+
     // only same as transpose if they're orthogonal
     //
+    // NOTE: nothing stops this dividing by a zero determinant; inverse() says when it would
+    //
     // adjugate matrix method:
     // A = Ã/|A|
     //
@@ -742,10 +1012,21 @@ impl Matrix {
         mat
     }
 
-    // TODO: is there a better way to create skew?
-    // pub fn skew(axis: Axis (plane? matrix?), angle: f32 ...) -> Self {
-    //     ...
-    // }
+    // slides the axis coordinate along by amount times the by coordinate, e.g.
+    // shear(Axis::X, Axis::Y, 0.5) moves x by half of y (a square becomes a parallelogram)
+    pub fn shear(axis: Axis, by: Axis, amount: f32) -> Self {
+        let mut mat = Self::identity();
+        if axis != by {
+            mat.rows[axis.index()][by.index()] = amount;
+        }
+        mat
+    }
+
+    // the same thing by angle: what was parallel to by leans rad toward axis
+    pub fn skew(axis: Axis, by: Axis, rad: f32) -> Self {
+        Matrix::shear(axis, by, rad.tan())
+    }
+
     pub fn basis(fu: Vec3, fv: Vec3, fw: Vec3) -> Self {
         let mut mat = Self::identity();
         if fu.normalize() != fu || fv.normalize() != fv || fw.normalize() != fw {
@@ -790,8 +1071,9 @@ impl Matrix {
         mat
     }
 
-    // TODO: there's a (imo better) version of this that can rotate around an
-    // arbitrary vector (or rotate one vector into another) rodrigues?
+    // NOTE: this one turns the *axes* by rad (its rows are the turned x, y and z, ready for
+    // CoordSys::new), so applied to points it moves them by -rad. The rotation_* below move
+    // points (rotation_about(rad, z) is rotation(rad, Axis::Z) transposed).
     pub fn rotation(rad: f32, axis: Axis) -> Self {
         let mut mat = Self::identity();
         match axis {
@@ -820,6 +1102,62 @@ impl Matrix {
     pub fn rotation_deg(deg: f32, axis: Axis) -> Self {
         Matrix::rotation(deg.to_radians(), axis)
     }
+
+    // turns points rad around axis (counterclockwise looking down it), by Rodrigues' formula:
+    // R = I + sin(rad) K + (1 - cos(rad)) K², K being the cross product with the (unit) axis
+    pub fn rotation_about(rad: f32, axis: Vec3) -> Self {
+        if axis.near_zero() {
+            return Self::identity();
+        }
+        let k = axis.normalize();
+        let (sin, cos) = rad.sin_cos();
+        let t = 1.0 - cos;
+        let (x, y, z) = (k.x(), k.y(), k.z());
+        Matrix::new([t*x*x + cos,   t*x*y - sin*z, t*x*z + sin*y, 0.0],
+                    [t*x*y + sin*z, t*y*y + cos,   t*y*z - sin*x, 0.0],
+                    [t*x*z - sin*y, t*y*z + sin*x, t*z*z + cos,   0.0],
+                    [0.0,           0.0,           0.0,           1.0])
+    }
+
+    pub fn rotation_about_deg(deg: f32, axis: Vec3) -> Self {
+        Matrix::rotation_about(deg.to_radians(), axis)
+    }
+
+    // the smallest rotation that turns direction from into direction to
+    pub fn rotation_onto(from: Vec3, to: Vec3) -> Self {
+        if from.near_zero() || to.near_zero() {
+            return Self::identity();
+        }
+        let (from, to) = (from.normalize(), to.normalize());
+        let cos = from.dot(to).clamp(-1.0, 1.0);
+        let axis = from.cross(to);
+        if axis.len() < 1e-6 {
+            if cos > 0.0 {
+                return Self::identity();
+            }
+            // opposite: any axis perpendicular to from will do
+            let (perp, _) = orthonormal_basis(from);
+            return Matrix::rotation_about(std::f32::consts::PI, perp);
+        }
+        Matrix::rotation_about(cos.acos(), axis)
+    }
+
+    // places things at from, looking at at: what's -z for them (the way cameras look, see
+    // Camera::init) points at at, and y is as close to up as it can be
+    pub fn look_at(from: Vec3, at: Vec3, up: Vec3) -> Self {
+        let w = if (from - at).near_zero() { Vec3::new([0.0, 0.0, 1.0]) } else { (from - at).normalize() };
+        let u = up.cross(w);
+        let (u, v) = if u.near_zero() {
+            orthonormal_basis(w) // (looking straight up or down: any sideways will do)
+        } else {
+            let u = u.normalize();
+            (u, w.cross(u))
+        };
+        Matrix::new([u.x(), v.x(), w.x(), from.x()],
+                    [u.y(), v.y(), w.y(), from.y()],
+                    [u.z(), v.z(), w.z(), from.z()],
+                    [0.0,   0.0,   0.0,   1.0])
+    }
 }
 
 // Would add/sub (and addassign/subassign) ever be needed?
@@ -1144,15 +1482,13 @@ impl Vec2 {
     }
 
     // vector with values in range [0,1)
-    pub fn rand() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn rand<R: Rng + ?Sized>(rng: &mut R) -> Self {
         let unit = Uniform::new(0.0, 1.0); // maybe more uniform than otherwise
         Self { v: [rng.sample(unit), rng.sample(unit)] }
     }
 
     // return vector of n purportedly well-distributed random Vecs
-    pub fn rand_arr(n: usize) -> Vec<Self> {
-        let mut rng = thread_rng();
+    pub fn rand_arr<R: Rng + ?Sized>(n: usize, rng: &mut R) -> Vec<Self> {
         let unitx = Uniform::new(0.0, 1.0); // maybe more uniform than otherwise
         let unity = Uniform::new(0.0, 1.0); // maybe more uniform than otherwise
         let mut ret = Vec::<Self>::new();
@@ -0,0 +1,70 @@
+// Matrix::inverse and CoordSys::from_matrix: what they take, and what they refuse
+
+use rtrtr::*;
+
+fn max_off_identity(m: &Matrix) -> f32 {
+    let mut off: f32 = 0.0;
+    for i in 0..4 {
+        for j in 0..4 {
+            off = off.max((m.rows[i][j] - if i == j { 1.0 } else { 0.0 }).abs());
+        }
+    }
+    off
+}
+
+#[test]
+fn rotation_far_from_the_origin() {
+    for far in [1e2, 3e4, 1e5, 1e6] {
+        let t = Transform::new()
+            .rotate(0.7, Vec3::new([1.0, 2.0, 3.0]))
+            .translate(Vec3::new([far, -far, far]));
+        let csys = t.build().unwrap_or_else(|e| panic!("translated by {}: {}", far, e));
+        // the linear part comes back exactly, and points make the round trip
+        let back = csys.m_in * csys.m_out;
+        for i in 0..3 {
+            for j in 0..3 {
+                assert!((back.rows[i][j] - if i == j { 1.0 } else { 0.0 }).abs() < 1e-5);
+            }
+        }
+        let p = Vec3::new([0.25, -0.5, 1.0]);
+        assert!((csys.point_in(csys.point_out(p)) - p).len() < 1e-6 * far);
+    }
+}
+
+#[test]
+fn zero_scale_is_singular() {
+    let t = Transform::new().scale(Vec3::new([1.0, 0.0, 1.0]));
+    assert_eq!(t.build().err(), Some(MatrixError::Singular));
+    assert_eq!(t.matrix.inverse().err(), Some(MatrixError::Singular));
+}
+
+#[test]
+fn skew_inverts() {
+    let m = Transform::new()
+        .scale(Vec3::new([0.5, 2.0, 1.0]))
+        .skew(Axis::X, Axis::Y, 0.3)
+        .translate(Vec3::new([1.0, -1.0, 2.0]))
+        .matrix;
+    let inv = m.inverse().unwrap();
+    assert!(max_off_identity(&(m * inv)) < 1e-5);
+    assert!(max_off_identity(&(inv * m)) < 1e-5);
+
+    // axes in a plane can't be turned around
+    let flat = CoordSys::new(Vec3::zero(), Vec3::new([1.0, 1.0, 1.0]),
+                             Vec3::new([1.0, 0.0, 0.0]), Vec3::new([0.0, 1.0, 0.0]), Vec3::new([1.0, 1.0, 0.0]));
+    assert_eq!(flat.err(), Some(MatrixError::Singular));
+}
+
+#[test]
+fn non_affine() {
+    let mut p = Matrix::identity();
+    p.rows[3][2] = -1.0;
+    p.rows[3][3] = 2.0; // (a projection, but an invertible one)
+    let inv = p.inverse().unwrap();
+    assert!(max_off_identity(&(p * inv)) < 1e-5);
+    assert_eq!(CoordSys::from_matrix(p).err(), Some(MatrixError::NotAffine));
+
+    let mut nan = Matrix::identity();
+    nan.rows[0][0] = f32::NAN;
+    assert_eq!(nan.inverse().err(), Some(MatrixError::NotFinite));
+}